@@ -1,13 +1,23 @@
 //! base64 encode/decode commands
 use std::{
     fmt::{Debug, Display},
+    io::{stdout, BufReader, BufWriter, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
 use clap::Parser;
+use colored::Colorize;
 use enum_dispatch::enum_dispatch;
 
-use crate::{process_decode, process_encode, verify_file, CmdExecutor};
+use crate::{
+    get_reader, hexdump, process_data_uri_decode, process_data_uri_decode_file,
+    process_data_uri_encode, process_decode, process_encode, read_data_uri_header, verify_file,
+    write_file_atomic, CmdExecutor,
+};
+
+/// bytes of binary output previewed on a terminal
+const PREVIEW_LEN: usize = 256;
 
 /// base64 encode/decode commands
 #[derive(Parser, Debug)]
@@ -24,14 +34,30 @@ pub enum Base64SubCommand {
 pub struct Base64EncodeOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
+    /// standard, standard-nopad, urlsafe, urlsafe-pad, mime, bcrypt or crypt
     #[arg(short, long, value_parser = parse_base64_format, default_value = "standard")]
     pub format: Base64Format,
+    /// emit a `data:<mime>;base64,` uri, the mime type is sniffed from the content or extension
+    #[arg(long)]
+    pub data_uri: bool,
 }
 
 impl CmdExecutor for Base64EncodeOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let encode = process_encode(&self.input, self.format)?;
-        println!("{encode}");
+        let mut reader = get_reader(&self.input)?;
+        let mut writer = BufWriter::new(stdout().lock());
+        if self.data_uri {
+            anyhow::ensure!(
+                matches!(self.format, Base64Format::Standard),
+                "Data uris only use the standard format"
+            );
+            let path = (self.input != "-").then(|| Path::new(&self.input));
+            process_data_uri_encode(&mut reader, &mut writer, path)?;
+        } else {
+            process_encode(&mut reader, &mut writer, self.format)?;
+        }
+        writeln!(writer)?;
+        writer.flush()?;
         Ok(())
     }
 }
@@ -41,24 +67,95 @@ impl CmdExecutor for Base64EncodeOpts {
 pub struct Base64DecodeOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
+    /// standard, standard-nopad, urlsafe, urlsafe-pad, mime, bcrypt, crypt,
+    /// or auto to detect the alphabet and padding
     #[arg(short, long, value_parser = parse_base64_format, default_value = "standard")]
     pub format: Base64Format,
+    /// write the decoded bytes to a file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// the input is a data uri, an output without extension gets the one of its mime type
+    #[arg(long)]
+    pub data_uri: bool,
 }
 
 impl CmdExecutor for Base64DecodeOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let decode = process_decode(&self.input, self.format)?;
-        let decode = String::from_utf8(decode)?;
-        println!("{decode}");
+        let mut reader = BufReader::new(get_reader(&self.input)?);
+        if let (true, Some(output)) = (self.data_uri, &self.output) {
+            let (uri, output) = process_data_uri_decode_file(&mut reader, output)?;
+            eprintln!(
+                "{}",
+                format!("✓ Wrote {} to {}", uri.mime, output.display()).green()
+            );
+            return Ok(());
+        }
+
+        let uri = if self.data_uri {
+            Some(read_data_uri_header(&mut reader)?)
+        } else {
+            None
+        };
+        let decode = |reader: &mut dyn Read, writer: &mut dyn Write| match &uri {
+            Some(uri) => process_data_uri_decode(reader, writer, uri),
+            None => process_decode(reader, writer, self.format),
+        };
+
+        if let Some(output) = &self.output {
+            // decode next to the output and rename, a bad input keeps the old file
+            return write_file_atomic(output, |writer| decode(&mut reader, writer));
+        }
+
+        let stdout = stdout();
+        if !stdout.is_terminal() {
+            // pipes and redirects get the raw bytes
+            let mut writer = BufWriter::new(stdout.lock());
+            decode(&mut reader, &mut writer)?;
+            writer.flush()?;
+            return Ok(());
+        }
+
+        let mut decoded = Vec::new();
+        decode(&mut reader, &mut decoded)?;
+        print_decoded(decoded);
         Ok(())
     }
 }
 
+/// print decoded bytes on a terminal, text as is and binary as a hexdump preview
+pub(super) fn print_decoded(decoded: Vec<u8>) {
+    match String::from_utf8(decoded) {
+        Ok(text) => println!("{text}"),
+        Err(e) => {
+            // binary would garble the terminal, show a preview instead
+            let decoded = e.into_bytes();
+            print!("{}", hexdump(&decoded[..decoded.len().min(PREVIEW_LEN)]));
+            if decoded.len() > PREVIEW_LEN {
+                println!("... {} more bytes", decoded.len() - PREVIEW_LEN);
+            }
+            eprintln!(
+                "{}",
+                format!(
+                    "⚠ {} bytes of binary data, use --output or a redirect to save them",
+                    decoded.len()
+                )
+                .bright_yellow()
+            );
+        }
+    }
+}
+
 /// command receiving format
 #[derive(Debug, Clone, Copy)]
 pub enum Base64Format {
     Standard,
+    StandardNoPad,
     UrlSafe,
+    UrlSafePad,
+    Mime,
+    Bcrypt,
+    Crypt,
+    Auto,
 }
 
 fn parse_base64_format(format: &str) -> Result<Base64Format, anyhow::Error> {
@@ -69,7 +166,13 @@ impl From<Base64Format> for &'static str {
     fn from(value: Base64Format) -> Self {
         match value {
             Base64Format::Standard => "standard",
+            Base64Format::StandardNoPad => "standard-nopad",
             Base64Format::UrlSafe => "urlsafe",
+            Base64Format::UrlSafePad => "urlsafe-pad",
+            Base64Format::Mime => "mime",
+            Base64Format::Bcrypt => "bcrypt",
+            Base64Format::Crypt => "crypt",
+            Base64Format::Auto => "auto",
         }
     }
 }
@@ -80,7 +183,13 @@ impl FromStr for Base64Format {
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         match s {
             "standard" => Ok(Base64Format::Standard),
+            "standard-nopad" => Ok(Base64Format::StandardNoPad),
             "urlsafe" => Ok(Base64Format::UrlSafe),
+            "urlsafe-pad" => Ok(Base64Format::UrlSafePad),
+            "mime" => Ok(Base64Format::Mime),
+            "bcrypt" => Ok(Base64Format::Bcrypt),
+            "crypt" => Ok(Base64Format::Crypt),
+            "auto" => Ok(Base64Format::Auto),
             _ => Err(anyhow::anyhow!("Invalid format")),
         }
     }
@@ -0,0 +1,128 @@
+//! encode/decode commands for every binary-to-text codec
+use std::{
+    fmt::Display,
+    io::{stdout, IsTerminal, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use clap::Parser;
+
+use super::base64::print_decoded;
+use crate::{
+    get_content, process_codec_decode, process_codec_encode, verify_file, write_file_atomic,
+    CmdExecutor, CodecOptions,
+};
+
+/// encode command
+#[derive(Parser, Debug)]
+pub struct EncodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    /// base64, hex, base32, base32-crockford, base58, base58check, base85 or z85
+    #[arg(short, long, value_parser = parse_codec, default_value = "hex")]
+    pub codec: Codec,
+    /// upper case hex digits, hex only
+    #[arg(long)]
+    pub upper: bool,
+    /// put between hex bytes: whitespace, '-', ':', ',' or '.', hex only
+    #[arg(long, default_value = "")]
+    pub separator: String,
+}
+
+/// decode command
+#[derive(Parser, Debug)]
+pub struct DecodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    /// base64, hex, base32, base32-crockford, base58, base58check, base85 or z85
+    #[arg(short, long, value_parser = parse_codec, default_value = "hex")]
+    pub codec: Codec,
+    /// write the decoded bytes to a file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Codec {
+    Base64,
+    Hex,
+    Base32,
+    Base32Crockford,
+    Base58,
+    Base58Check,
+    Base85,
+    Z85,
+}
+
+impl CmdExecutor for EncodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let data = get_content(&self.input)?;
+        let options = CodecOptions {
+            codec: self.codec,
+            upper: self.upper,
+            separator: self.separator,
+        };
+        println!("{}", process_codec_encode(&data, &options)?);
+        Ok(())
+    }
+}
+
+impl CmdExecutor for DecodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let text = String::from_utf8(get_content(&self.input)?)?;
+        let decoded = process_codec_decode(&text, self.codec)?;
+        if let Some(output) = &self.output {
+            // a failed write keeps the old file, like base64 decode
+            write_file_atomic(output, |writer| Ok(writer.write_all(&decoded)?))?;
+        } else if stdout().is_terminal() {
+            print_decoded(decoded);
+        } else {
+            stdout().lock().write_all(&decoded)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_codec(codec: &str) -> Result<Codec, anyhow::Error> {
+    codec.parse()
+}
+
+impl From<Codec> for &'static str {
+    fn from(value: Codec) -> Self {
+        match value {
+            Codec::Base64 => "base64",
+            Codec::Hex => "hex",
+            Codec::Base32 => "base32",
+            Codec::Base32Crockford => "base32-crockford",
+            Codec::Base58 => "base58",
+            Codec::Base58Check => "base58check",
+            Codec::Base85 => "base85",
+            Codec::Z85 => "z85",
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "base64" => Ok(Codec::Base64),
+            "hex" => Ok(Codec::Hex),
+            "base32" => Ok(Codec::Base32),
+            "base32-crockford" => Ok(Codec::Base32Crockford),
+            "base58" => Ok(Codec::Base58),
+            "base58check" => Ok(Codec::Base58Check),
+            "base85" => Ok(Codec::Base85),
+            "z85" => Ok(Codec::Z85),
+            _ => Err(anyhow::anyhow!("Invalid codec")),
+        }
+    }
+}
+
+impl Display for Codec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
@@ -1,9 +1,12 @@
 //! csv command
-use std::{fmt::Display, str::FromStr};
+use std::{fmt::Display, str::FromStr, time::Duration};
 
 use clap::Parser;
 
-use crate::{process_csv, verify_file, CmdExecutor};
+use crate::{
+    get_content, is_xml_name, process_csv, process_csv_decode, process_csv_watch, verify_file,
+    CmdExecutor, CsvAnonymizer, CsvWriteOptions,
+};
 
 /// support types of output format
 #[derive(Debug, Clone, Copy)]
@@ -11,6 +14,21 @@ pub enum OutputFormat {
     Json,
     Yaml,
     Toml,
+    Xml,
+    MsgPack,
+    Cbor,
+    Parquet,
+    Arrow,
+}
+
+/// compression codecs of columnar output
+#[derive(Debug, Clone, Copy)]
+pub enum CsvCompression {
+    None,
+    Snappy,
+    Gzip,
+    Lz4,
+    Zstd,
 }
 
 impl Display for OutputFormat {
@@ -23,7 +41,7 @@ impl Display for OutputFormat {
 #[derive(Parser, Debug)]
 pub struct CsvOpts {
     /// Input file path
-    #[arg(short, long, value_parser = verify_file)]
+    #[arg(short, long, value_parser = verify_csv_file)]
     pub input: String,
     /// Output file path
     #[arg(short, long)] // "output.json".into()
@@ -37,10 +55,51 @@ pub struct CsvOpts {
     /// CSV has header or not
     #[arg(long, default_value_t = true)]
     pub header: bool,
+    /// Columns whose values are redacted
+    #[arg(long)]
+    pub mask: Vec<String>,
+    /// Columns replaced by a keyed blake3 token, as COLUMN=KEYFILE
+    #[arg(long, value_parser = parse_pseudonymize)]
+    pub pseudonymize: Vec<(String, String)>,
+    /// Root element name of xml output
+    #[arg(long, value_parser = verify_xml_name, default_value = "rows")]
+    pub xml_root: String,
+    /// Element name of every record in xml output
+    #[arg(long, value_parser = verify_xml_name, default_value = "row")]
+    pub xml_row: String,
+    /// Compression of parquet/arrow output
+    #[arg(long, value_parser = parse_compression, default_value = "none")]
+    pub compression: CsvCompression,
+    /// Rows per parquet row group or arrow record batch
+    #[arg(long, default_value_t = 1024 * 1024)]
+    pub row_group_size: usize,
+    /// Keep running and convert again whenever the input changes
+    #[arg(long, default_value_t = false)]
+    pub watch: bool,
+    /// Milliseconds to wait for further writes before converting in watch mode
+    #[arg(long, default_value_t = 500)]
+    pub debounce: u64,
+    /// Decode a msgpack/cbor input of the given format and print it as json
+    #[arg(long, default_value_t = false)]
+    pub decode: bool,
+}
+
+/// csv is read from a path, and --watch needs one to watch, so stdin is rejected
+fn verify_csv_file(filename: &str) -> Result<String, &'static str> {
+    if filename == "-" {
+        return Err("CSV input must be a file, stdin can't be read or watched");
+    }
+    verify_file(filename)
 }
 
 impl CmdExecutor for CsvOpts {
     async fn execute(self) -> anyhow::Result<()> {
+        if self.decode {
+            let content = process_csv_decode(&self.input, self.format)?;
+            println!("{content}");
+            return Ok(());
+        }
+
         // 如果这个output这个字段没有被设置, 则使用output.{format}来作为缺省值
         let output = if let Some(output) = self.output {
             output
@@ -48,7 +107,35 @@ impl CmdExecutor for CsvOpts {
             format!("output.{}", self.format)
         };
 
-        process_csv(&self.input, output, self.format).await?;
+        let mut anonymizer = CsvAnonymizer::default();
+        for column in self.mask {
+            anonymizer = anonymizer.mask(column);
+        }
+        for (column, key) in self.pseudonymize {
+            anonymizer = anonymizer.pseudonymize(column, get_content(&key)?)?;
+        }
+
+        let options = CsvWriteOptions {
+            xml_root: self.xml_root,
+            xml_row: self.xml_row,
+            compression: self.compression,
+            row_group_size: self.row_group_size,
+        };
+
+        if self.watch {
+            let debounce = Duration::from_millis(self.debounce);
+            process_csv_watch(
+                &self.input,
+                output,
+                self.format,
+                &anonymizer,
+                &options,
+                debounce,
+            )
+            .await?;
+        } else {
+            process_csv(&self.input, output, self.format, &anonymizer, &options).await?;
+        }
         Ok(())
     }
 }
@@ -57,12 +144,33 @@ fn parse_format(format: &str) -> Result<OutputFormat, anyhow::Error> {
     format.parse::<OutputFormat>()
 }
 
+fn parse_pseudonymize(value: &str) -> Result<(String, String), anyhow::Error> {
+    let (column, key) = value
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected COLUMN=KEYFILE"))?;
+    let key = verify_file(key).map_err(anyhow::Error::msg)?;
+    Ok((column.to_string(), key))
+}
+
+fn verify_xml_name(name: &str) -> Result<String, &'static str> {
+    if is_xml_name(name) {
+        Ok(name.into())
+    } else {
+        Err("Invalid xml element name")
+    }
+}
+
 impl From<OutputFormat> for &'static str {
     fn from(value: OutputFormat) -> Self {
         match value {
             OutputFormat::Json => "json",
             OutputFormat::Yaml => "yaml",
             OutputFormat::Toml => "toml",
+            OutputFormat::Xml => "xml",
+            OutputFormat::MsgPack => "msgpack",
+            OutputFormat::Cbor => "cbor",
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Arrow => "arrow",
         }
     }
 }
@@ -75,7 +183,61 @@ impl FromStr for OutputFormat {
             "json" => Ok(OutputFormat::Json),
             "yaml" => Ok(OutputFormat::Yaml),
             "toml" => Ok(OutputFormat::Toml),
+            "xml" => Ok(OutputFormat::Xml),
+            "msgpack" => Ok(OutputFormat::MsgPack),
+            "cbor" => Ok(OutputFormat::Cbor),
+            "parquet" => Ok(OutputFormat::Parquet),
+            "arrow" => Ok(OutputFormat::Arrow),
             _ => Err(anyhow::anyhow!("Invalid format")),
         }
     }
 }
+
+fn parse_compression(compression: &str) -> Result<CsvCompression, anyhow::Error> {
+    compression.parse()
+}
+
+impl From<CsvCompression> for &'static str {
+    fn from(value: CsvCompression) -> Self {
+        match value {
+            CsvCompression::None => "none",
+            CsvCompression::Snappy => "snappy",
+            CsvCompression::Gzip => "gzip",
+            CsvCompression::Lz4 => "lz4",
+            CsvCompression::Zstd => "zstd",
+        }
+    }
+}
+
+impl FromStr for CsvCompression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(CsvCompression::None),
+            "snappy" => Ok(CsvCompression::Snappy),
+            "gzip" => Ok(CsvCompression::Gzip),
+            "lz4" => Ok(CsvCompression::Lz4),
+            "zstd" => Ok(CsvCompression::Zstd),
+            _ => Err(anyhow::anyhow!("Invalid compression")),
+        }
+    }
+}
+
+impl Display for CsvCompression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_watch_rejects_stdin() {
+        let parse = |input: &str| CsvOpts::try_parse_from(["csv", "-i", input, "--watch"]);
+        assert!(parse("-").is_err());
+        assert!(parse("fixtures/juventus.csv").is_ok_and(|opts| opts.watch));
+    }
+}
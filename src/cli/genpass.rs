@@ -1,49 +1,855 @@
 //! genpass command
-use crate::{process_genpass, CmdExecutor};
-use clap::Parser;
+use std::{fmt::Display, io::IsTerminal, path::PathBuf, str::FromStr};
+
+use super::seeded_rng;
+use crate::{
+    default_policy_file, get_content, get_reader, load_policy, process_genpass_derive,
+    process_genpass_with_rng, process_genpassphrase_with_rng, process_genpattern_with_rng,
+    process_genpronounceable_with_rng, process_gentoken_with_rng, process_password_check,
+    process_password_strength, process_strong_password, verify_file, CmdExecutor,
+    PassphraseOptions, PasswordPolicy, PasswordReport, Pattern, PronounceableOptions,
+    PwnedPasswords, TokenOptions,
+};
+use clap::{ArgAction, ArgGroup, Parser};
 use colored::Colorize;
-use zxcvbn::zxcvbn;
+use enum_dispatch::enum_dispatch;
+use rand::{rngs::OsRng, CryptoRng, RngCore};
 
 /// genpass command
 #[derive(Parser, Debug)]
+#[command(
+    args_conflicts_with_subcommands = true,
+    group(ArgGroup::new("regenerate").args(["min_score", "hibp"]).multiple(true)),
+)]
 pub struct GenPassOpts {
-    /// generate password length
-    #[arg(short, long, default_value_t = 16)]
-    pub length: u8,
+    #[command(subcommand)]
+    pub cmd: Option<GenPassSubCommand>,
+
+    #[command(flatten)]
+    pub policy_opts: PasswordPolicyOpts,
+
+    /// generate a token or identifier instead: hex, base64url, base32, uuid4, uuid7, ulid or pin
+    #[arg(long, value_parser = parse_kind, conflicts_with_all = [
+        "words", "policy", "charset", "uppercase", "lowercase", "number", "symbol",
+        "min_uppercase", "min_lowercase", "min_number", "min_symbol", "exclude_ambiguous",
+        "symbols", "exclude",
+    ])]
+    pub kind: Option<TokenKind>,
+
+    /// random bytes of hex, base64url and base32 tokens [default: 32]
+    #[arg(long, requires = "kind")]
+    pub bytes: Option<u16>,
+
+    /// generate an easy to dictate password of consonant/vowel syllables instead,
+    /// followed by --min-number digits and --min-symbol symbols
+    #[arg(long, conflicts_with_all = [
+        "words", "kind", "policy", "charset", "uppercase", "lowercase", "number", "symbol",
+        "min_uppercase", "min_lowercase", "exclude_ambiguous", "exclude",
+    ])]
+    pub pronounceable: bool,
+
+    /// generate a string matching a pattern instead: a template where X, x, 9 and ! stand
+    /// for an uppercase letter, lowercase letter, digit and symbol, or, once it contains
+    /// `[`, `{` or `\`, a regex subset such as `[A-Z]{3}-\d{4}` where they are literal
+    #[arg(long, value_parser = parse_pattern, conflicts_with_all = [
+        "words", "kind", "pronounceable", "policy", "length", "charset", "uppercase",
+        "lowercase", "number", "symbol", "min_uppercase", "min_lowercase", "min_number",
+        "min_symbol", "exclude_ambiguous", "symbols", "exclude",
+    ])]
+    pub pattern: Option<Pattern>,
+
+    /// seed a deterministic rng, for tests only
+    #[arg(long, hide = true)]
+    pub seed: Option<u64>,
+
+    /// generate a diceware passphrase with this many words instead,
+    /// --symbols, --exclude and --exclude-ambiguous apply to the appended characters
+    #[arg(long, conflicts_with_all = [
+        "kind", "pronounceable", "pattern", "policy", "length", "charset", "uppercase",
+        "lowercase", "number", "symbol", "min_uppercase", "min_lowercase", "min_number",
+        "min_symbol",
+    ])]
+    pub words: Option<u8>,
+
+    /// passphrase word separator
+    #[arg(long, default_value = "-", requires = "words")]
+    pub separator: String,
+
+    /// capitalize every passphrase word
+    #[arg(long, requires = "words")]
+    pub capitalize: bool,
+
+    /// append a random digit to the passphrase
+    #[arg(long, requires = "words")]
+    pub append_digit: bool,
+
+    /// append a random symbol to the passphrase
+    #[arg(long, requires = "words")]
+    pub append_symbol: bool,
+
+    /// regenerate until the zxcvbn score reaches this
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=4))]
+    pub min_score: Option<u8>,
+
+    /// give up after this many attempts to reach the minimum score or a password
+    /// missing from --hibp
+    #[arg(long, default_value_t = 100, requires = "regenerate")]
+    pub max_attempts: u32,
+
+    /// words the password must not be guessable from, like the username or company name
+    #[arg(short, long = "user-input")]
+    pub user_inputs: Vec<String>,
+
+    /// reject passwords found in a local Pwned Passwords file or range directory
+    #[arg(long)]
+    pub hibp: Option<String>,
+
+    /// number of passwords to generate
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub count: u32,
+
+    /// script friendly output: plain, json or csv [default: plain when piped]
+    #[arg(long, value_parser = parse_format)]
+    pub format: Option<GenPassFormat>,
+}
 
-    /// generate password whether support or not uppercase
-    #[arg(long, default_value_t = true)]
+/// password policy options shared by the genpass commands
+#[derive(Parser, Debug)]
+pub struct PasswordPolicyOpts {
+    /// generate password length [default: 16]
+    #[arg(short, long)]
+    pub length: Option<u8>,
+
+    /// generate password without uppercase
+    #[arg(long = "no-uppercase", action = ArgAction::SetFalse)]
     pub uppercase: bool,
 
-    /// generate password whether support or not lowercase
-    #[arg(long, default_value_t = true)]
+    /// generate password without lowercase
+    #[arg(long = "no-lowercase", action = ArgAction::SetFalse)]
     pub lowercase: bool,
 
-    /// generate password whether support or not number
-    #[arg(long, default_value_t = true)]
+    /// generate password without number
+    #[arg(long = "no-number", action = ArgAction::SetFalse)]
     pub number: bool,
 
-    /// generate password whether support or not symbol
-    #[arg(long, default_value_t = true)]
+    /// generate password without symbol
+    #[arg(long = "no-symbol", action = ArgAction::SetFalse)]
     pub symbol: bool,
+
+    /// minimum count of uppercase characters [default: 1]
+    #[arg(long, conflicts_with = "uppercase")]
+    pub min_uppercase: Option<u8>,
+
+    /// minimum count of lowercase characters [default: 1]
+    #[arg(long, conflicts_with = "lowercase")]
+    pub min_lowercase: Option<u8>,
+
+    /// minimum count of numbers [default: 1]
+    #[arg(long, conflicts_with = "number")]
+    pub min_number: Option<u8>,
+
+    /// minimum count of symbols [default: 1]
+    #[arg(long, conflicts_with = "symbol")]
+    pub min_symbol: Option<u8>,
+
+    /// drop easily confused characters (0/O/1/l/I)
+    #[arg(long)]
+    pub exclude_ambiguous: bool,
+
+    /// allowed symbols [default: !@#$%^&*_]
+    #[arg(long, conflicts_with = "symbol")]
+    pub symbols: Option<String>,
+
+    /// completely custom alphabet instead of the character classes
+    #[arg(long, conflicts_with_all = [
+        "uppercase", "lowercase", "number", "symbol",
+        "min_uppercase", "min_lowercase", "min_number", "min_symbol", "symbols",
+    ])]
+    pub charset: Option<String>,
+
+    /// characters the target system rejects
+    #[arg(long)]
+    pub exclude: Option<String>,
+
+    /// named policy to start from, the options above override it
+    #[arg(long)]
+    pub policy: Option<String>,
+
+    /// toml file with one table per policy
+    /// [default: $RCLI_POLICY_FILE or ~/.config/rcli/policies.toml]
+    #[arg(long, requires = "policy")]
+    pub policy_file: Option<String>,
+
+    /// deprecated, every class is on by default, use --no-uppercase to turn it off
+    #[arg(long = "uppercase", hide = true)]
+    pub legacy_uppercase: bool,
+
+    /// deprecated, every class is on by default, use --no-lowercase to turn it off
+    #[arg(long = "lowercase", hide = true)]
+    pub legacy_lowercase: bool,
+
+    /// deprecated, every class is on by default, use --no-number to turn it off
+    #[arg(long = "number", hide = true)]
+    pub legacy_number: bool,
+
+    /// deprecated, every class is on by default, use --no-symbol to turn it off
+    #[arg(long = "symbol", hide = true)]
+    pub legacy_symbol: bool,
+}
+
+impl PasswordPolicyOpts {
+    /// the named policy (or the default one) with the command line overrides applied
+    fn policy(&self) -> anyhow::Result<PasswordPolicy> {
+        self.warn_legacy_flags();
+        let mut policy = match &self.policy {
+            Some(name) => {
+                let file = self
+                    .policy_file
+                    .as_ref()
+                    .map(PathBuf::from)
+                    .or_else(default_policy_file)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "No policy file, pass --policy-file or set RCLI_POLICY_FILE"
+                        )
+                    })?;
+                load_policy(file, name)?
+            }
+            None => PasswordPolicy::default(),
+        };
+
+        if let Some(length) = self.length {
+            policy.length = length as usize;
+        }
+        policy.uppercase &= self.uppercase;
+        policy.lowercase &= self.lowercase;
+        policy.number &= self.number;
+        policy.symbol &= self.symbol;
+        for (min, value) in [
+            (&mut policy.min_uppercase, self.min_uppercase),
+            (&mut policy.min_lowercase, self.min_lowercase),
+            (&mut policy.min_number, self.min_number),
+            (&mut policy.min_symbol, self.min_symbol),
+        ] {
+            if let Some(value) = value {
+                *min = value as usize;
+            }
+        }
+        policy.exclude_ambiguous |= self.exclude_ambiguous;
+        if let Some(symbols) = &self.symbols {
+            policy.symbols = Some(symbols.clone());
+        }
+        if let Some(charset) = &self.charset {
+            policy.charset = Some(charset.clone());
+        }
+        if let Some(exclude) = &self.exclude {
+            policy.forbidden.push_str(exclude);
+        }
+
+        Ok(policy)
+    }
+
+    /// the old `--uppercase`-style flags were always on, they are accepted as no-ops
+    fn warn_legacy_flags(&self) {
+        for (used, flag) in [
+            (self.legacy_uppercase, "uppercase"),
+            (self.legacy_lowercase, "lowercase"),
+            (self.legacy_number, "number"),
+            (self.legacy_symbol, "symbol"),
+        ] {
+            if used {
+                eprintln!(
+                    "{}",
+                    format!("⚠ --{flag} is deprecated and has no effect, use --no-{flag} to turn it off")
+                        .bright_yellow()
+                );
+            }
+        }
+    }
+}
+
+/// genpass subcommands
+#[derive(Parser, Debug)]
+#[enum_dispatch(CmdExecutor)]
+pub enum GenPassSubCommand {
+    #[command(name = "check", about = "Check the strength of passwords")]
+    Check(GenPassCheckOpts),
+    #[command(
+        name = "derive",
+        about = "Derive a reproducible site password from a master secret"
+    )]
+    Derive(GenPassDeriveOpts),
+}
+
+/// site password derive command
+#[derive(Parser, Debug)]
+pub struct GenPassDeriveOpts {
+    /// site the password is for
+    #[arg(long)]
+    pub site: String,
+
+    /// user name on the site
+    #[arg(long, default_value = "")]
+    pub user: String,
+
+    /// bump to rotate the password
+    #[arg(long, default_value_t = 1)]
+    pub counter: u32,
+
+    /// file holding the master secret, prompted for when missing
+    #[arg(short, long, value_parser = verify_file)]
+    pub key: Option<String>,
+
+    /// key derivation function: argon2 or blake3
+    #[arg(long, value_parser = parse_kdf, default_value = "argon2")]
+    pub kdf: DeriveKdf,
+
+    #[command(flatten)]
+    pub policy_opts: PasswordPolicyOpts,
+}
+
+/// key derivation functions of genpass derive
+#[derive(Debug, Clone, Copy)]
+pub enum DeriveKdf {
+    Argon2,
+    Blake3,
+}
+
+/// password strength check command
+#[derive(Parser, Debug)]
+pub struct GenPassCheckOpts {
+    /// file with one candidate password per line
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// words an attacker may know, like the username or company name
+    #[arg(short, long = "user-input")]
+    pub user_inputs: Vec<String>,
+
+    /// fail when any password scores below this
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=4))]
+    pub min_score: Option<u8>,
+
+    /// look passwords up in a local Pwned Passwords file or range directory
+    #[arg(long)]
+    pub hibp: Option<String>,
+
+    /// script friendly output: plain, json or csv
+    #[arg(long, value_parser = parse_format)]
+    pub format: Option<GenPassFormat>,
+}
+
+/// kinds of tokens and identifiers
+#[derive(Debug, Clone, Copy)]
+pub enum TokenKind {
+    Hex,
+    Base64Url,
+    Base32,
+    Uuid4,
+    Uuid7,
+    Ulid,
+    Pin,
+}
+
+/// structured output formats of generated passwords
+#[derive(Debug, Clone, Copy)]
+pub enum GenPassFormat {
+    Plain,
+    Json,
+    Csv,
+}
+
+/// a password generator of one mode, called once per attempt
+type Generator = Box<dyn FnMut() -> anyhow::Result<String>>;
+
+impl GenPassOpts {
+    /// generate the requested passwords or passphrases along with their strength
+    fn generate(&self) -> anyhow::Result<Vec<PasswordReport>> {
+        let (mut generate, entropy) = match self.seed {
+            Some(seed) => self.generator(seeded_rng(seed))?,
+            None => self.generator(OsRng)?,
+        };
+
+        let user_inputs = self
+            .user_inputs
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let pwned = self
+            .hibp
+            .as_ref()
+            .map(PwnedPasswords::try_new)
+            .transpose()?;
+        let min_score = self.min_score.unwrap_or_default();
+        let max_attempts = if self.min_score.is_some() || pwned.is_some() {
+            self.max_attempts
+        } else {
+            1
+        };
+
+        (0..self.count)
+            .map(|_| {
+                process_strong_password(
+                    &mut generate,
+                    entropy,
+                    min_score,
+                    max_attempts,
+                    &user_inputs,
+                    pwned.as_ref(),
+                )
+            })
+            .collect()
+    }
+
+    /// the requested format, pipes and redirects get plain passwords by default so
+    /// `rcli genpass | rcli hash-password` only sees the passwords
+    fn output_format(&self, terminal: bool) -> Option<GenPassFormat> {
+        self.format.or((!terminal).then_some(GenPassFormat::Plain))
+    }
+
+    /// pick the generator of the requested mode, drawing every random choice from `rng`
+    fn generator<R: RngCore + CryptoRng + 'static>(
+        &self,
+        mut rng: R,
+    ) -> anyhow::Result<(Generator, f64)> {
+        let generator: (Generator, _) = if let Some(words) = self.words {
+            let options = PassphraseOptions {
+                words: words as usize,
+                separator: self.separator.clone(),
+                capitalize: self.capitalize,
+                digit: self.append_digit,
+                symbol: self.append_symbol,
+                symbol_set: self.policy_opts.symbols.clone(),
+                exclude_ambiguous: self.policy_opts.exclude_ambiguous,
+                forbidden: self.policy_opts.exclude.clone().unwrap_or_default(),
+            };
+            let entropy = options.entropy();
+            (
+                Box::new(move || process_genpassphrase_with_rng(&mut rng, &options)),
+                entropy,
+            )
+        } else if self.pronounceable {
+            let policy_opts = &self.policy_opts;
+            let options = PronounceableOptions {
+                length: policy_opts.length.unwrap_or(16) as usize,
+                digits: policy_opts.min_number.unwrap_or_default() as usize,
+                symbols: policy_opts.min_symbol.unwrap_or_default() as usize,
+                symbol_set: policy_opts.symbols.clone(),
+            };
+            let entropy = options.entropy();
+            (
+                Box::new(move || process_genpronounceable_with_rng(&mut rng, &options)),
+                entropy,
+            )
+        } else if let Some(pattern) = self.pattern.clone() {
+            let entropy = pattern.entropy();
+            (
+                Box::new(move || process_genpattern_with_rng(&mut rng, &pattern)),
+                entropy,
+            )
+        } else if let Some(kind) = self.kind {
+            let random = matches!(
+                kind,
+                TokenKind::Hex | TokenKind::Base64Url | TokenKind::Base32
+            );
+            anyhow::ensure!(
+                random || self.bytes.is_none(),
+                "--bytes only applies to hex, base64url and base32 tokens, not {kind}"
+            );
+            anyhow::ensure!(
+                matches!(kind, TokenKind::Pin) || self.policy_opts.length.is_none(),
+                "--length only applies to pins, use --bytes for the size of a {kind} token"
+            );
+            let options = TokenOptions {
+                kind,
+                bytes: self.bytes.unwrap_or(32) as usize,
+                // pins are usually short
+                length: self.policy_opts.length.unwrap_or(6) as usize,
+            };
+            let entropy = options.entropy();
+            (
+                Box::new(move || process_gentoken_with_rng(&mut rng, &options)),
+                entropy,
+            )
+        } else {
+            let policy = self.policy_opts.policy()?;
+            let entropy = policy.entropy()?;
+            (
+                Box::new(move || process_genpass_with_rng(&mut rng, &policy)),
+                entropy,
+            )
+        };
+        Ok(generator)
+    }
 }
 
 impl CmdExecutor for GenPassOpts {
+    async fn execute(mut self) -> anyhow::Result<()> {
+        if let Some(cmd) = self.cmd.take() {
+            return cmd.execute().await;
+        }
+
+        let reports = self.generate()?;
+        let strong = self.min_score.unwrap_or(3);
+
+        match self.output_format(std::io::stdout().is_terminal()) {
+            None => {
+                for report in reports {
+                    println!("generate password: {}", report.password.purple());
+                    // test password strength
+                    let score = report.score.to_string();
+                    let score = if report.score >= strong {
+                        score.green()
+                    } else {
+                        score.red()
+                    };
+                    println!("Password strength: {score}");
+                    println!(
+                        "Password entropy: {} bits",
+                        format!("{:.1}", report.entropy).red()
+                    );
+                }
+            }
+            Some(GenPassFormat::Plain) => {
+                for report in reports {
+                    println!("{}", report.password);
+                }
+            }
+            Some(GenPassFormat::Json) => println!("{}", serde_json::to_string_pretty(&reports)?),
+            Some(GenPassFormat::Csv) => {
+                let mut writer = csv::Writer::from_writer(std::io::stdout());
+                for report in reports {
+                    writer.serialize(report)?;
+                }
+                writer.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl CmdExecutor for GenPassCheckOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let password = process_genpass(
-            self.length,
-            self.uppercase,
-            self.lowercase,
-            self.number,
-            self.symbol,
+        let mut reader = get_reader(&self.input)?;
+        let user_inputs = self
+            .user_inputs
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let pwned = self
+            .hibp
+            .as_ref()
+            .map(PwnedPasswords::try_new)
+            .transpose()?;
+        let reports = process_password_check(&mut reader, &user_inputs, pwned.as_ref())?;
+        let weak = self
+            .min_score
+            .map(|min| reports.iter().filter(|r| r.score < min).count())
+            .unwrap_or_default();
+        let leaked = reports
+            .iter()
+            .filter(|r| r.pwned.unwrap_or_default() > 0)
+            .count();
+
+        match self.format {
+            None => {
+                for report in &reports {
+                    let score = report.score.to_string();
+                    let score = match self.min_score {
+                        Some(min) if report.score < min => score.bright_red(),
+                        _ => score.bright_green(),
+                    };
+                    println!("\n{}{}", "password: ".bright_purple(), report.password);
+                    println!("  score: {score}, guesses: 10^{:.1}", report.guesses_log10);
+                    println!(
+                        "  crack time: {} (online, throttled), {} (online), {} (offline, slow hash), {} (offline, fast hash)",
+                        report.crack_time_online_throttled,
+                        report.crack_time_online,
+                        report.crack_time_offline_slow,
+                        report.crack_time_offline_fast,
+                    );
+                    if !report.warning.is_empty() {
+                        println!("  {} {}", "warning:".bright_red(), report.warning);
+                    }
+                    if !report.suggestions.is_empty() {
+                        println!("  suggestions: {}", report.suggestions);
+                    }
+                    match report.pwned {
+                        Some(0) => println!("  {}", "✓ not found in breaches".bright_green()),
+                        Some(count) => println!(
+                            "  {}",
+                            format!("⚠ seen {count} times in breaches").bright_red()
+                        ),
+                        None => {}
+                    }
+                }
+            }
+            Some(GenPassFormat::Plain) => {
+                for report in &reports {
+                    println!("{}\t{}", report.score, report.password);
+                }
+            }
+            Some(GenPassFormat::Json) => println!("{}", serde_json::to_string_pretty(&reports)?),
+            Some(GenPassFormat::Csv) => {
+                let mut writer = csv::Writer::from_writer(std::io::stdout());
+                for report in &reports {
+                    writer.serialize(report)?;
+                }
+                writer.flush()?;
+            }
+        }
+
+        if weak > 0 {
+            anyhow::bail!(
+                "{weak} password(s) score below {}",
+                self.min_score.unwrap_or_default()
+            );
+        }
+        if leaked > 0 {
+            anyhow::bail!("{leaked} password(s) found in breaches");
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for GenPassDeriveOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let master = match &self.key {
+            Some(key) => {
+                let mut master = get_content(key)?;
+                // files written with `echo` end with a newline that isn't part of the secret
+                while master.last().is_some_and(|c| *c == b'\n' || *c == b'\r') {
+                    master.pop();
+                }
+                master
+            }
+            None => rpassword::prompt_password("Master secret: ")?.into_bytes(),
+        };
+        anyhow::ensure!(!master.is_empty(), "Master secret must not be empty");
+
+        let policy = self.policy_opts.policy()?;
+        let password = process_genpass_derive(
+            &master,
+            &self.site,
+            &self.user,
+            self.counter,
+            self.kdf,
+            &policy,
         )?;
+        let report = process_password_strength(&password, Some(policy.entropy()?), &[]);
+
+        println!("derived password: {}", report.password.purple());
+        println!("Password strength: {}", report.score.to_string().green());
+        Ok(())
+    }
+}
+
+fn parse_format(format: &str) -> Result<GenPassFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl From<GenPassFormat> for &'static str {
+    fn from(value: GenPassFormat) -> Self {
+        match value {
+            GenPassFormat::Plain => "plain",
+            GenPassFormat::Json => "json",
+            GenPassFormat::Csv => "csv",
+        }
+    }
+}
+
+impl FromStr for GenPassFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(GenPassFormat::Plain),
+            "json" => Ok(GenPassFormat::Json),
+            "csv" => Ok(GenPassFormat::Csv),
+            _ => Err(anyhow::anyhow!("Invalid format")),
+        }
+    }
+}
+
+impl Display for GenPassFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+fn parse_kdf(kdf: &str) -> Result<DeriveKdf, anyhow::Error> {
+    kdf.parse()
+}
 
-        println!("generate password: {}", password.purple());
+impl From<DeriveKdf> for &'static str {
+    fn from(value: DeriveKdf) -> Self {
+        match value {
+            DeriveKdf::Argon2 => "argon2",
+            DeriveKdf::Blake3 => "blake3",
+        }
+    }
+}
+
+impl FromStr for DeriveKdf {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "argon2" => Ok(DeriveKdf::Argon2),
+            "blake3" => Ok(DeriveKdf::Blake3),
+            _ => Err(anyhow::anyhow!("Invalid kdf")),
+        }
+    }
+}
+
+impl Display for DeriveKdf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Result<Pattern, anyhow::Error> {
+    pattern.parse()
+}
+
+fn parse_kind(kind: &str) -> Result<TokenKind, anyhow::Error> {
+    kind.parse()
+}
+
+impl From<TokenKind> for &'static str {
+    fn from(value: TokenKind) -> Self {
+        match value {
+            TokenKind::Hex => "hex",
+            TokenKind::Base64Url => "base64url",
+            TokenKind::Base32 => "base32",
+            TokenKind::Uuid4 => "uuid4",
+            TokenKind::Uuid7 => "uuid7",
+            TokenKind::Ulid => "ulid",
+            TokenKind::Pin => "pin",
+        }
+    }
+}
+
+impl FromStr for TokenKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(TokenKind::Hex),
+            "base64url" => Ok(TokenKind::Base64Url),
+            "base32" => Ok(TokenKind::Base32),
+            "uuid4" => Ok(TokenKind::Uuid4),
+            "uuid7" => Ok(TokenKind::Uuid7),
+            "ulid" => Ok(TokenKind::Ulid),
+            "pin" => Ok(TokenKind::Pin),
+            _ => Err(anyhow::anyhow!("Invalid kind")),
+        }
+    }
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<GenPassOpts, clap::Error> {
+        GenPassOpts::try_parse_from(std::iter::once("genpass").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_pronounceable_conflicts() {
+        assert!(parse(&["--pronounceable", "--min-number", "2", "--min-symbol", "1"]).is_ok());
+        for flag in [
+            "--no-number",
+            "--no-symbol",
+            "--no-uppercase",
+            "--exclude-ambiguous",
+        ] {
+            assert!(parse(&["--pronounceable", flag]).is_err(), "{flag}");
+        }
+    }
 
-        // test password strength
-        let result = zxcvbn(&password, &[]);
-        println!("Password strength: {}", result.score().to_string().red());
+    #[test]
+    fn test_words_conflicts() {
+        assert!(parse(&[
+            "--words",
+            "3",
+            "--append-symbol",
+            "--symbols",
+            "#",
+            "--exclude",
+            "#"
+        ])
+        .is_ok());
+        for args in [
+            &["--charset", "abc"][..],
+            &["--min-number", "4"],
+            &["--no-symbol", "--append-symbol"],
+            &["--length", "8"],
+            &["--pronounceable"],
+        ] {
+            let args = [&["--words", "3"][..], args].concat();
+            assert!(parse(&args).is_err(), "{args:?}");
+        }
+    }
 
+    #[test]
+    fn test_output_format() -> Result<(), clap::Error> {
+        let opts = parse(&[])?;
+        assert!(opts.output_format(true).is_none());
+        assert!(matches!(
+            opts.output_format(false),
+            Some(GenPassFormat::Plain)
+        ));
+        let opts = parse(&["--format", "json"])?;
+        assert!(matches!(
+            opts.output_format(false),
+            Some(GenPassFormat::Json)
+        ));
         Ok(())
     }
+
+    #[test]
+    fn test_max_attempts_requires() {
+        let hibp = ["--hibp", "fixtures/pwned.txt"];
+        assert!(parse(&[&hibp[..], &["--max-attempts", "3"]].concat()).is_ok());
+        assert!(parse(&["--min-score", "3", "--max-attempts", "3"]).is_ok());
+        assert!(parse(&[&hibp[..], &["--min-score", "3", "--max-attempts", "3"]].concat()).is_ok());
+        assert!(parse(&["--max-attempts", "3"]).is_err());
+    }
+
+    #[test]
+    fn test_kind_sizes() -> Result<(), clap::Error> {
+        let generate = |args: &[&str]| parse(args).map(|opts| opts.generator(OsRng).is_ok());
+        assert!(generate(&["--kind", "pin", "--length", "8"])?);
+        assert!(generate(&["--kind", "hex", "--bytes", "8"])?);
+        assert!(!generate(&["--kind", "hex", "--length", "8"])?);
+        assert!(!generate(&["--kind", "uuid4", "--length", "8"])?);
+        assert!(!generate(&["--kind", "pin", "--bytes", "4"])?);
+        assert!(!generate(&["--kind", "ulid", "--bytes", "4"])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_kind_conflicts() {
+        assert!(parse(&["--kind", "pin", "--length", "8"]).is_ok());
+        assert!(parse(&["--kind", "hex", "--bytes", "16"]).is_ok());
+        for args in [
+            &["--no-symbol"][..],
+            &["--exclude", "0123456789"],
+            &["--charset", "abc"],
+            &["--min-number", "2"],
+            &["--symbols", "!"],
+            &["--exclude-ambiguous"],
+        ] {
+            let args = [&["--kind", "pin"][..], args].concat();
+            assert!(parse(&args).is_err(), "{args:?}");
+        }
+    }
 }
@@ -1,13 +1,19 @@
 mod base64;
+mod codec;
 mod csv;
 mod genpass;
 mod http;
+mod otp;
+mod password;
 mod text;
 
-pub use self::{base64::*, csv::*, genpass::*, http::*, text::*};
+pub use self::{base64::*, codec::*, csv::*, genpass::*, http::*, otp::*, password::*, text::*};
 
 use clap::Parser;
+use colored::Colorize;
 use enum_dispatch::enum_dispatch;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
 
 #[derive(Parser, Debug)]
 #[command(name = "rcli", version, author, about = "This is a cli with a lot of commands", long_about = None)]
@@ -16,6 +22,8 @@ pub struct Opts {
     pub cmd: SubCommand,
 }
 
+// parsed once per run, so the size of the genpass options doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Parser, Debug)]
 #[enum_dispatch(CmdExecutor)]
 pub enum SubCommand {
@@ -23,10 +31,42 @@ pub enum SubCommand {
     Csv(CsvOpts),
     #[command(name = "genpass", about = "Generate a random password")]
     GenPass(GenPassOpts),
+    #[command(name = "hash-password", about = "Hash passwords into PHC strings")]
+    HashPassword(HashPasswordOpts),
+    #[command(
+        name = "verify-password",
+        about = "Verify a password against a PHC string"
+    )]
+    VerifyPassword(VerifyPasswordOpts),
     #[command(subcommand, about = "Base64 encode/decode")]
     Base64(Base64SubCommand),
+    #[command(
+        name = "encode",
+        about = "Encode to hex, base32, base58, base85 and more"
+    )]
+    Encode(EncodeOpts),
+    #[command(
+        name = "decode",
+        about = "Decode from hex, base32, base58, base85 and more"
+    )]
+    Decode(DecodeOpts),
     #[command(subcommand, about = "Text sign/verify")]
     Text(TextSubCommand),
     #[command(subcommand, about = "Http server")]
     Http(HttpSubCommand),
+    #[command(subcommand, about = "HOTP/TOTP one-time passwords")]
+    Otp(OtpSubCommand),
+}
+
+/// a deterministic rng for reproducible test output, loudly warned about
+fn seeded_rng(seed: u64) -> ChaCha20Rng {
+    eprintln!(
+        "{}",
+        format!(
+            "⚠ WARNING: --seed {seed} makes the output predictable, never use it for real secrets"
+        )
+        .bright_red()
+        .bold()
+    );
+    ChaCha20Rng::seed_from_u64(seed)
 }
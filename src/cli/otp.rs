@@ -0,0 +1,269 @@
+//! one-time password commands
+use std::{fmt::Display, str::FromStr};
+
+use clap::Parser;
+use colored::Colorize;
+use enum_dispatch::enum_dispatch;
+
+use crate::{
+    process_otp_code, process_otp_secret, process_otp_uri, process_otp_verify, unix_time,
+    CmdExecutor, OtpParams, MAX_OTP_WINDOW,
+};
+
+/// HOTP/TOTP one-time password commands
+#[derive(Parser, Debug)]
+#[enum_dispatch(CmdExecutor)]
+pub enum OtpSubCommand {
+    #[command(name = "secret", about = "Generate a base32 shared secret")]
+    Secret(OtpSecretOpts),
+    #[command(name = "uri", about = "Print an otpauth:// uri for authenticator apps")]
+    Uri(OtpUriOpts),
+    #[command(name = "code", about = "Compute the current code")]
+    Code(OtpCodeOpts),
+    #[command(name = "verify", about = "Verify a code within a drift window")]
+    Verify(OtpVerifyOpts),
+}
+
+/// generate secret command
+#[derive(Parser, Debug)]
+pub struct OtpSecretOpts {
+    /// random bytes of the secret
+    #[arg(long, default_value_t = 20)]
+    pub bytes: u16,
+}
+
+/// secret and code parameters shared by uri, code and verify
+#[derive(Parser, Debug)]
+pub struct OtpParamsOpts {
+    /// base32 shared secret
+    #[arg(short, long)]
+    pub secret: String,
+
+    /// totp (time based) or hotp (counter based)
+    #[arg(short, long, value_parser = parse_otp_kind, default_value = "totp")]
+    pub kind: OtpKind,
+
+    /// hmac algorithm: sha1, sha256 or sha512
+    #[arg(short, long, value_parser = parse_otp_algorithm, default_value = "sha1")]
+    pub algorithm: OtpAlgorithm,
+
+    /// code length
+    #[arg(short, long, default_value_t = 6, value_parser = clap::value_parser!(u32).range(6..=9))]
+    pub digits: u32,
+
+    /// totp time step in seconds
+    #[arg(long, default_value_t = 30)]
+    pub period: u64,
+
+    /// hotp counter
+    #[arg(short, long, default_value_t = 0)]
+    pub counter: u64,
+
+    /// totp unix time instead of now
+    #[arg(long)]
+    pub time: Option<u64>,
+}
+
+/// otpauth uri command
+#[derive(Parser, Debug)]
+pub struct OtpUriOpts {
+    #[command(flatten)]
+    pub params: OtpParamsOpts,
+
+    /// service name shown by the authenticator
+    #[arg(long, default_value = "")]
+    pub issuer: String,
+
+    /// account name shown by the authenticator
+    #[arg(long)]
+    pub account: String,
+}
+
+/// current code command
+#[derive(Parser, Debug)]
+pub struct OtpCodeOpts {
+    #[command(flatten)]
+    pub params: OtpParamsOpts,
+}
+
+/// verify code command
+#[derive(Parser, Debug)]
+pub struct OtpVerifyOpts {
+    #[command(flatten)]
+    pub params: OtpParamsOpts,
+
+    /// the code to check
+    #[arg(long)]
+    pub code: String,
+
+    /// steps accepted on either side of the current totp step, or ahead of the hotp counter
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(0..=MAX_OTP_WINDOW))]
+    pub window: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum OtpKind {
+    Totp,
+    Hotp,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum OtpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl OtpParamsOpts {
+    fn params(&self) -> anyhow::Result<OtpParams> {
+        OtpParams::try_new(&self.secret, self.algorithm, self.digits, self.period)
+    }
+
+    /// the hotp counter, or the totp time step
+    fn counter(&self, params: &OtpParams) -> anyhow::Result<u64> {
+        Ok(match self.kind {
+            OtpKind::Hotp => self.counter,
+            OtpKind::Totp => params.time_step(self.time()?),
+        })
+    }
+
+    fn time(&self) -> anyhow::Result<u64> {
+        self.time.map_or_else(unix_time, Ok)
+    }
+}
+
+impl CmdExecutor for OtpSecretOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        println!("{}", process_otp_secret(self.bytes as usize)?);
+        Ok(())
+    }
+}
+
+impl CmdExecutor for OtpUriOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let params = self.params.params()?;
+        let uri = process_otp_uri(
+            &params,
+            self.params.kind,
+            &self.issuer,
+            &self.account,
+            self.params.counter,
+        );
+        println!("{uri}");
+        Ok(())
+    }
+}
+
+impl CmdExecutor for OtpCodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let params = self.params.params()?;
+        let code = process_otp_code(&params, self.params.counter(&params)?)?;
+        println!("{}", code.bright_green().bold());
+        if let OtpKind::Totp = self.params.kind {
+            let remaining = params.period - self.params.time()? % params.period;
+            eprintln!("{}", format!("valid for {remaining}s").bright_black());
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for OtpVerifyOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let params = self.params.params()?;
+        let counter = self.params.counter(&params)?;
+        // a hotp counter only moves forward
+        let behind = match self.params.kind {
+            OtpKind::Totp => self.window,
+            OtpKind::Hotp => 0,
+        };
+        match process_otp_verify(&params, &self.code, counter, behind, self.window)? {
+            Some(0) => println!("{}", "✓ Code verified".green()),
+            Some(drift) => println!(
+                "{} {}",
+                "✓ Code verified".green(),
+                format!("(drift {drift:+} steps)").bright_yellow()
+            ),
+            None => anyhow::bail!("Code not verified"),
+        }
+        Ok(())
+    }
+}
+
+fn parse_otp_kind(kind: &str) -> Result<OtpKind, anyhow::Error> {
+    kind.parse()
+}
+
+fn parse_otp_algorithm(algorithm: &str) -> Result<OtpAlgorithm, anyhow::Error> {
+    algorithm.parse()
+}
+
+impl From<OtpKind> for &'static str {
+    fn from(value: OtpKind) -> Self {
+        match value {
+            OtpKind::Totp => "totp",
+            OtpKind::Hotp => "hotp",
+        }
+    }
+}
+
+impl FromStr for OtpKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "totp" => Ok(OtpKind::Totp),
+            "hotp" => Ok(OtpKind::Hotp),
+            _ => Err(anyhow::anyhow!("Invalid kind")),
+        }
+    }
+}
+
+impl Display for OtpKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl From<OtpAlgorithm> for &'static str {
+    fn from(value: OtpAlgorithm) -> Self {
+        match value {
+            OtpAlgorithm::Sha1 => "sha1",
+            OtpAlgorithm::Sha256 => "sha256",
+            OtpAlgorithm::Sha512 => "sha512",
+        }
+    }
+}
+
+impl FromStr for OtpAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "sha1" => Ok(OtpAlgorithm::Sha1),
+            "sha256" => Ok(OtpAlgorithm::Sha256),
+            "sha512" => Ok(OtpAlgorithm::Sha512),
+            _ => Err(anyhow::anyhow!("Invalid algorithm")),
+        }
+    }
+}
+
+impl Display for OtpAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_otp_verify_window_range() {
+        let parse = |window: &str| {
+            OtpVerifyOpts::try_parse_from(["verify", "-s", "GEZDGNBV", "--code", "1", "-w", window])
+        };
+        assert_eq!(parse("10").map(|opts| opts.window).ok(), Some(10));
+        assert!(parse("11").is_err());
+        assert!(parse("18446744073709551615").is_err());
+    }
+}
@@ -0,0 +1,153 @@
+//! Password hashing commands
+use std::{
+    fmt::Display,
+    io::{stdin, IsTerminal},
+    str::FromStr,
+};
+
+use clap::Parser;
+use colored::Colorize;
+
+use crate::{
+    get_content, get_reader, process_password_hash_lines, process_password_verify, verify_file,
+    CmdExecutor, PasswordHashParams,
+};
+
+/// Hash passwords, one per line, into PHC strings
+#[derive(Parser, Debug)]
+pub struct HashPasswordOpts {
+    /// passwords, one per line, e.g. piped from `rcli genpass`
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// hash algorithm: argon2id, bcrypt or scrypt
+    #[arg(short, long, value_parser = parse_algorithm, default_value = "argon2id")]
+    pub algorithm: HashAlgorithm,
+
+    /// argon2id memory in KiB
+    #[arg(long, default_value_t = 19456)]
+    pub memory: u32,
+
+    /// argon2id passes over the memory
+    #[arg(long, default_value_t = 2)]
+    pub iterations: u32,
+
+    /// argon2id lanes or scrypt parallelism
+    #[arg(long, default_value_t = 1)]
+    pub parallelism: u32,
+
+    /// bcrypt cost, log2 of the number of rounds
+    #[arg(long, default_value_t = 12)]
+    pub cost: u32,
+
+    /// scrypt cost, log2 of N
+    #[arg(long, default_value_t = 17)]
+    pub log_n: u8,
+
+    /// scrypt block size
+    #[arg(long, default_value_t = 8)]
+    pub block_size: u32,
+
+    /// print the plaintext and the hash separated by a tab
+    #[arg(long)]
+    pub with_plaintext: bool,
+}
+
+/// Verify a password against a PHC string
+#[derive(Parser, Debug)]
+pub struct VerifyPasswordOpts {
+    /// PHC string, or a bcrypt `$2b$` string
+    #[arg(long)]
+    pub hash: String,
+
+    /// file holding the password, stdin when piped, prompted otherwise
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum HashAlgorithm {
+    Argon2id,
+    Bcrypt,
+    Scrypt,
+}
+
+impl CmdExecutor for HashPasswordOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let params = match self.algorithm {
+            HashAlgorithm::Argon2id => PasswordHashParams::Argon2id {
+                memory: self.memory,
+                iterations: self.iterations,
+                parallelism: self.parallelism,
+            },
+            HashAlgorithm::Bcrypt => PasswordHashParams::Bcrypt { cost: self.cost },
+            HashAlgorithm::Scrypt => PasswordHashParams::Scrypt {
+                log_n: self.log_n,
+                r: self.block_size,
+                p: self.parallelism,
+            },
+        };
+        let mut reader = get_reader(&self.input)?;
+        for (password, hash) in process_password_hash_lines(&mut reader, params)? {
+            if self.with_plaintext {
+                println!("{password}\t{hash}");
+            } else {
+                println!("{hash}");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for VerifyPasswordOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let password = match self.input.as_deref() {
+            None if stdin().is_terminal() => rpassword::prompt_password("Password: ")?,
+            input => {
+                let content = String::from_utf8(get_content(input.unwrap_or("-"))?)?;
+                // only the first line, like the hash-password input
+                content.lines().next().unwrap_or_default().to_string()
+            }
+        };
+
+        if process_password_verify(&password, &self.hash)? {
+            println!("{}", "✓ Password verified".green());
+            Ok(())
+        } else {
+            anyhow::bail!("Password does not match the hash")
+        }
+    }
+}
+
+fn parse_algorithm(algorithm: &str) -> Result<HashAlgorithm, anyhow::Error> {
+    algorithm.parse()
+}
+
+impl From<HashAlgorithm> for &'static str {
+    fn from(value: HashAlgorithm) -> Self {
+        match value {
+            HashAlgorithm::Argon2id => "argon2id",
+            HashAlgorithm::Bcrypt => "bcrypt",
+            HashAlgorithm::Scrypt => "scrypt",
+        }
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "argon2id" => Ok(HashAlgorithm::Argon2id),
+            "bcrypt" => Ok(HashAlgorithm::Bcrypt),
+            "scrypt" => Ok(HashAlgorithm::Scrypt),
+            _ => Err(anyhow::anyhow!("Invalid algorithm")),
+        }
+    }
+}
+
+impl Display for HashAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
@@ -11,9 +11,10 @@ use colored::Colorize;
 use enum_dispatch::enum_dispatch;
 use tokio::fs;
 
+use super::seeded_rng;
 use crate::{
-    get_content, get_reader, process_text_key_generate, process_text_sign, process_text_verify,
-    verify_file, verify_path, CmdExecutor,
+    get_content, get_reader, process_text_key_generate, process_text_key_generate_with_rng,
+    process_text_sign, process_text_verify, verify_file, verify_path, CmdExecutor,
 };
 
 /// Text signature commands
@@ -87,11 +88,18 @@ pub struct TextKeyGenerateOpts {
 
     #[arg(short, long, value_parser = verify_path)]
     pub output: PathBuf,
+
+    /// seed a deterministic rng, for tests only
+    #[arg(long, hide = true)]
+    pub seed: Option<u64>,
 }
 
 impl CmdExecutor for TextKeyGenerateOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let key = process_text_key_generate(self.format)?;
+        let key = match self.seed {
+            Some(seed) => process_text_key_generate_with_rng(self.format, &mut seeded_rng(seed))?,
+            None => process_text_key_generate(self.format)?,
+        };
         for (k, v) in key {
             fs::write(self.output.join(k), v).await?;
         }
@@ -1,39 +1,355 @@
 //! decode content encoding into content
 
-use crate::{cli::Base64Format, get_reader};
-use anyhow::Result;
+use crate::cli::Base64Format;
+use anyhow::{anyhow, bail, Result};
 use base64::{
-    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
-    Engine as _,
+    alphabet::{self, Alphabet},
+    engine::{
+        general_purpose::{GeneralPurpose, NO_PAD, PAD},
+        DecodePaddingMode,
+    },
+    read::DecoderReader,
+    write::EncoderWriter,
+    DecodeError, Engine,
 };
-use std::io::Read;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
 
-/// encode content to base64
-pub fn process_encode(input: &str, format: Base64Format) -> Result<String> {
-    let mut reader = get_reader(input)?;
-    let mut buf = Vec::new();
-    reader.read_to_end(&mut buf)?;
+/// line length of MIME encoded content, RFC 2045
+const MIME_LINE_LEN: usize = 76;
+/// encoded bytes looked at by `--format auto`
+const DETECT_LEN: u64 = 64 * 1024;
 
-    let encode = match format {
-        Base64Format::Standard => STANDARD.encode(buf),
-        Base64Format::UrlSafe => URL_SAFE_NO_PAD.encode(buf),
+const STANDARD: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, PAD);
+const STANDARD_NO_PAD: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, NO_PAD);
+const URL_SAFE: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, PAD);
+const URL_SAFE_NO_PAD: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, NO_PAD);
+const BCRYPT: GeneralPurpose = GeneralPurpose::new(&alphabet::BCRYPT, NO_PAD);
+const CRYPT: GeneralPurpose = GeneralPurpose::new(&alphabet::CRYPT, NO_PAD);
+
+/// the alphabet and engine of a format, `auto` is resolved before this
+fn engine(format: Base64Format) -> Result<(&'static Alphabet, GeneralPurpose)> {
+    let engine = match format {
+        Base64Format::Standard | Base64Format::Mime => (&alphabet::STANDARD, STANDARD),
+        Base64Format::StandardNoPad => (&alphabet::STANDARD, STANDARD_NO_PAD),
+        Base64Format::UrlSafe => (&alphabet::URL_SAFE, URL_SAFE_NO_PAD),
+        Base64Format::UrlSafePad => (&alphabet::URL_SAFE, URL_SAFE),
+        Base64Format::Bcrypt => (&alphabet::BCRYPT, BCRYPT),
+        Base64Format::Crypt => (&alphabet::CRYPT, CRYPT),
+        Base64Format::Auto => bail!("Format auto only detects the alphabet when decoding"),
+    };
+    Ok(engine)
+}
+
+/// encode content to base64, streaming from the reader to the writer
+pub fn process_encode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    format: Base64Format,
+) -> Result<()> {
+    let (_, engine) = engine(format)?;
+    let mut wrapped;
+    let writer: &mut dyn Write = if let Base64Format::Mime = format {
+        wrapped = LineWrap::new(writer, MIME_LINE_LEN);
+        &mut wrapped
+    } else {
+        writer
+    };
+    let mut encoder = EncoderWriter::new(writer, &engine);
+    io::copy(reader, &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+/// base64 decode to content, streaming from the reader to the writer,
+/// line breaks and other whitespace in the encoded input are skipped
+pub fn process_decode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    format: Base64Format,
+) -> Result<()> {
+    let (reader, (alphabet, engine)) = match format {
+        Base64Format::Auto => {
+            let mut prefix = Vec::new();
+            reader.take(DETECT_LEN).read_to_end(&mut prefix)?;
+            // a short input is seen whole, so its padding is known
+            let complete = (prefix.len() as u64) < DETECT_LEN;
+            if complete && bcrypt_hash(&prefix).is_some() {
+                return decode_bcrypt_hash(&prefix, writer);
+            }
+            let (alphabet, padding) = detect_format(&prefix, complete)?;
+            let engine = GeneralPurpose::new(alphabet, PAD.with_decode_padding_mode(padding));
+            let reader: Box<dyn Read + '_> = Box::new(Cursor::new(prefix).chain(reader));
+            (reader, (alphabet, engine))
+        }
+        format => {
+            let reader: Box<dyn Read + '_> = Box::new(reader);
+            (reader, engine(format)?)
+        }
     };
-    Ok(encode)
+
+    let mut reader = Base64Filter::new(BufReader::new(reader), alphabet);
+    let ret = io::copy(&mut DecoderReader::new(&mut reader, &engine), writer);
+    if let Err(e) = ret {
+        return Err(reader.describe(e));
+    }
+    Ok(())
 }
 
-/// base64 decode to content
-pub fn process_decode(input: &str, format: Base64Format) -> Result<Vec<u8>> {
-    let mut reader = get_reader(input)?;
-    let mut buf = String::new();
-    reader.read_to_string(&mut buf)?;
-    let buf = buf.trim();
+/// tell the alphabets apart by their extra characters, and the padding by the end of
+/// the input when all of it is in `prefix`
+///
+/// `.` only occurs in the bcrypt and crypt alphabets, they have the same characters in
+/// another order, so bcrypt is preferred and crypt has to be picked with --format.
+fn detect_format(prefix: &[u8], complete: bool) -> Result<(&'static Alphabet, DecodePaddingMode)> {
+    let has = |chars: &[u8]| prefix.iter().any(|b| chars.contains(b));
+    let alphabet = match (has(b"+"), has(b"/"), has(b"-_"), has(b".")) {
+        (_, _, true, true) | (true, _, _, true) => {
+            bail!("Input mixes the bcrypt/crypt (./) and other alphabets")
+        }
+        (_, _, _, true) => &alphabet::BCRYPT,
+        (true, _, true, _) | (_, true, true, _) => {
+            bail!("Input mixes the standard (+/) and url-safe (-_) alphabets")
+        }
+        (_, _, true, _) => &alphabet::URL_SAFE,
+        _ => &alphabet::STANDARD,
+    };
 
-    let decode = match format {
-        Base64Format::Standard => STANDARD.decode(buf)?,
-        Base64Format::UrlSafe => URL_SAFE_NO_PAD.decode(buf)?,
+    let padded = prefix
+        .iter()
+        .rev()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|&b| b == b'=');
+    let padding = match (padded, complete) {
+        (true, _) => DecodePaddingMode::RequireCanonical,
+        (false, true) => DecodePaddingMode::RequireNone,
+        // the end is still to come, accept it either way
+        (false, false) => DecodePaddingMode::Indifferent,
     };
+    Ok((alphabet, padding))
+}
+
+/// the offset and length of the salt and hash of a `$2b$12$<salt><hash>` string
+fn bcrypt_hash(input: &[u8]) -> Option<[(usize, usize); 2]> {
+    let start = input.iter().position(|b| !b.is_ascii_whitespace())?;
+    let hash = input[start..].trim_ascii_end();
+    let shaped = hash.len() == 60
+        && hash.starts_with(b"$2")
+        && b"abxy".contains(&hash[2])
+        && hash[3] == b'$'
+        && hash[4..6].iter().all(u8::is_ascii_digit)
+        && hash[6] == b'$'
+        && hash[7..]
+            .iter()
+            .all(|&b| alphabet::BCRYPT.as_str().as_bytes().contains(&b));
+    shaped.then_some([(start + 7, 22), (start + 29, 31)])
+}
+
+/// decode a bcrypt hash string into its 16 byte salt followed by its 23 byte hash
+fn decode_bcrypt_hash(input: &[u8], writer: &mut dyn Write) -> Result<()> {
+    let parts = bcrypt_hash(input).ok_or_else(|| anyhow!("Input is not a bcrypt hash"))?;
+    for (start, len) in parts {
+        let decoded = BCRYPT
+            .decode(&input[start..start + len])
+            .map_err(|e| match e {
+                DecodeError::InvalidByte(offset, b) | DecodeError::InvalidLastSymbol(offset, b) => {
+                    anyhow!(
+                        "Invalid base64 character {:?} at offset {}",
+                        b as char,
+                        start + offset
+                    )
+                }
+                e => anyhow!("Invalid bcrypt hash: {e}"),
+            })?;
+        writer.write_all(&decoded)?;
+    }
+    Ok(())
+}
+
+/// a reader dropping ascii whitespace, so wrapped base64 decodes in chunks,
+/// and rejecting bad characters and padding with their offset in the input
+struct Base64Filter<R> {
+    inner: R,
+    offset: u64,
+    checker: Base64Checker,
+}
+
+/// what the filter has seen so far, to tell where the padding may go
+struct Base64Checker {
+    allowed: [bool; 256],
+    /// symbols before the padding
+    symbols: u64,
+    /// offset of the last symbol before the padding
+    last_symbol: Option<u64>,
+    /// offset of the first `=` and the number of them
+    padding: Option<(u64, u64)>,
+}
+
+impl<R: BufRead> Base64Filter<R> {
+    fn new(inner: R, alphabet: &Alphabet) -> Self {
+        let mut allowed = [false; 256];
+        for &b in alphabet.as_str().as_bytes().iter().chain(b"=") {
+            allowed[b as usize] = true;
+        }
+        Self {
+            inner,
+            offset: 0,
+            checker: Base64Checker {
+                allowed,
+                symbols: 0,
+                last_symbol: None,
+                padding: None,
+            },
+        }
+    }
+
+    /// turn an error of the decoder, whose offsets ignore whitespace, into one with
+    /// input offsets
+    fn describe(&self, e: io::Error) -> anyhow::Error {
+        let Some(e) = e.get_ref().and_then(|e| e.downcast_ref::<DecodeError>()) else {
+            return e.into();
+        };
+        let checker = &self.checker;
+        let last = checker.last_symbol.unwrap_or_default();
+        match (e, checker.padding) {
+            (DecodeError::InvalidLastSymbol(_, b), _) => anyhow!(
+                "Invalid last base64 character {:?} at offset {last}, its unused bits aren't zero",
+                *b as char
+            ),
+            (DecodeError::InvalidLength(_), _) => {
+                anyhow!("Truncated base64, a lone character at offset {last} ends the input")
+            }
+            (DecodeError::InvalidPadding, None) => {
+                let end = checker.last_symbol.map_or(0, |offset| offset + 1);
+                anyhow!("Missing base64 padding at offset {end}")
+            }
+            (
+                DecodeError::InvalidPadding | DecodeError::InvalidByte(_, b'='),
+                Some((offset, pads)),
+            ) if pads < checker.needed() => {
+                anyhow!("Incomplete base64 padding at offset {offset}")
+            }
+            (
+                DecodeError::InvalidPadding | DecodeError::InvalidByte(_, b'='),
+                Some((offset, _)),
+            ) => {
+                anyhow!("Unexpected base64 padding at offset {offset}, the format has none")
+            }
+            (DecodeError::InvalidByte(_, b), _) => anyhow!(
+                "Invalid base64 character {:?} before offset {}",
+                *b as char,
+                self.offset
+            ),
+        }
+    }
+}
+
+impl Base64Checker {
+    /// `=` needed after the symbols so far: 2 symbols in the last group take 2,
+    /// 3 take 1, and a full group none
+    fn needed(&self) -> u64 {
+        [0, 0, 2, 1][(self.symbols % 4) as usize]
+    }
+
+    /// check a single non-whitespace byte at `offset`
+    fn check(&mut self, b: u8, offset: u64) -> Result<(), String> {
+        if !self.allowed[b as usize] {
+            return Err(format!(
+                "Invalid base64 character {:?} at offset {offset}",
+                b as char
+            ));
+        }
+        let needed = self.needed();
+        match (b, &mut self.padding) {
+            (b'=', Some((_, pads))) if *pads < needed => *pads += 1,
+            (b'=', None) if needed > 0 => self.padding = Some((offset, 1)),
+            (b'=', _) => return Err(format!("Invalid base64 padding at offset {offset}")),
+            (b, Some(_)) => {
+                return Err(format!(
+                    "Invalid base64 character {:?} at offset {offset} after the padding",
+                    b as char
+                ))
+            }
+            (_, None) => {
+                self.symbols += 1;
+                self.last_symbol = Some(offset);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for Base64Filter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let available = self.inner.fill_buf()?;
+            if available.is_empty() {
+                return Ok(0);
+            }
+            let mut n = 0;
+            let mut consumed = 0;
+            for &b in available {
+                if n == buf.len() {
+                    break;
+                }
+                if b.is_ascii_whitespace() {
+                    consumed += 1;
+                    continue;
+                }
+                self.checker
+                    .check(b, self.offset + consumed as u64)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                buf[n] = b;
+                n += 1;
+                consumed += 1;
+            }
+            self.inner.consume(consumed);
+            self.offset += consumed as u64;
+            // a chunk of only whitespace isn't the end of the input
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+        }
+    }
+}
+
+/// a writer breaking its output into crlf terminated lines
+struct LineWrap<W> {
+    inner: W,
+    width: usize,
+    column: usize,
+}
+
+impl<W: Write> LineWrap<W> {
+    fn new(inner: W, width: usize) -> Self {
+        Self {
+            inner,
+            width,
+            column: 0,
+        }
+    }
+}
 
-    Ok(decode)
+impl<W: Write> Write for LineWrap<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // the base64 encoder doesn't cope with short writes, take all of it
+        let mut rest = buf;
+        while !rest.is_empty() {
+            // break lazily, so the output doesn't end with an empty line
+            if self.column == self.width {
+                self.inner.write_all(b"\r\n")?;
+                self.column = 0;
+            }
+            let len = rest.len().min(self.width - self.column);
+            self.inner.write_all(&rest[..len])?;
+            self.column += len;
+            rest = &rest[len..];
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
 }
 
 #[cfg(test)]
@@ -41,20 +357,211 @@ mod tests {
     use anyhow::Result;
 
     use super::*;
+    use crate::{get_reader, write_file_atomic};
 
     #[test]
     fn test_process_encode() -> Result<()> {
-        let input = "Cargo.toml";
+        let mut reader = get_reader("Cargo.toml")?;
+        let mut buf = Vec::new();
         let format = Base64Format::Standard;
-        assert!(process_encode(input, format).is_ok());
+        assert!(process_encode(&mut reader, &mut buf, format).is_ok());
+        assert!(!buf.is_empty());
         Ok(())
     }
 
     #[test]
     fn test_process_decode() -> Result<()> {
-        let input = "fixtures/b64.txt";
+        let mut reader = get_reader("fixtures/b64.txt")?;
+        let mut buf = Vec::new();
+        let format = Base64Format::Standard;
+        assert!(process_decode(&mut reader, &mut buf, format).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decode_binary_file() -> Result<()> {
+        let key = std::fs::read("fixtures/ed25519.sk")?;
+        let format = Base64Format::Standard;
+        let mut encoded = Vec::new();
+        process_encode(
+            &mut get_reader("fixtures/ed25519.sk")?,
+            &mut encoded,
+            format,
+        )?;
+
+        let output = &std::env::temp_dir().join("rcli-test-decode.sk");
+        write_file_atomic(output, |writer| {
+            process_decode(&mut encoded.as_slice(), writer, format)
+        })?;
+        assert_eq!(std::fs::read(output)?, key);
+
+        // a failing decode leaves the decoded key in place
+        let ret = write_file_atomic(output, |writer| {
+            process_decode(&mut "not*base64".as_bytes(), writer, format)
+        });
+        assert!(ret.is_err());
+        assert_eq!(std::fs::read(output)?, key);
+        std::fs::remove_file(output)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decode_wrapped() -> Result<()> {
+        // larger than the copy buffers, so several chunks go through
+        let data = (0..200_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        for format in [Base64Format::Standard, Base64Format::UrlSafe] {
+            let mut encoded = Vec::new();
+            process_encode(&mut data.as_slice(), &mut encoded, format)?;
+
+            // wrap at 76 columns with crlf, like mime
+            let wrapped = encoded
+                .chunks(76)
+                .flat_map(|line| [line, b"\r\n"].concat())
+                .collect::<Vec<_>>();
+            let mut decoded = Vec::new();
+            process_decode(&mut wrapped.as_slice(), &mut decoded, format)?;
+            assert_eq!(decoded, data);
+        }
+
+        let mut decoded = Vec::new();
         let format = Base64Format::Standard;
-        assert!(process_decode(input, format).is_ok());
+        assert!(process_decode(&mut "not*base64".as_bytes(), &mut decoded, format).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_base64_formats() -> Result<()> {
+        let data = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+        for format in [
+            Base64Format::Standard,
+            Base64Format::StandardNoPad,
+            Base64Format::UrlSafe,
+            Base64Format::UrlSafePad,
+            Base64Format::Mime,
+            Base64Format::Bcrypt,
+            Base64Format::Crypt,
+        ] {
+            let mut encoded = Vec::new();
+            process_encode(&mut data.as_slice(), &mut encoded, format)?;
+            let mut decoded = Vec::new();
+            process_decode(&mut encoded.as_slice(), &mut decoded, format)?;
+            assert_eq!(decoded, data, "{format}");
+
+            // crypt has the characters of bcrypt in another order, auto picks bcrypt
+            if !matches!(format, Base64Format::Crypt) {
+                let mut decoded = Vec::new();
+                process_decode(&mut encoded.as_slice(), &mut decoded, Base64Format::Auto)?;
+                assert_eq!(decoded, data, "auto {format}");
+            }
+            if let Base64Format::Mime = format {
+                let text = String::from_utf8(encoded)?;
+                assert!(text.split("\r\n").all(|line| line.len() <= MIME_LINE_LEN));
+                assert!(!text.ends_with("\r\n"));
+            }
+        }
+
+        let mut decoded = Vec::new();
+        let err = process_decode(
+            &mut "aGVs\nbG8*".as_bytes(),
+            &mut decoded,
+            Base64Format::Auto,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Invalid base64 character '*' at offset 8");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decode_error_offsets() {
+        let decode = |input: &str, format| {
+            let mut decoded = Vec::new();
+            process_decode(&mut input.as_bytes(), &mut decoded, format)
+                .unwrap_err()
+                .to_string()
+        };
+        let standard = Base64Format::Standard;
+        for (input, format, expected) in [
+            // offsets count the whitespace the decoder never sees
+            (
+                "aGVs\r\n=bG8=",
+                standard,
+                "Invalid base64 padding at offset 6",
+            ),
+            (
+                "aGk=\nx",
+                standard,
+                "Invalid base64 character 'x' at offset 5 after the padding",
+            ),
+            ("aG=\n=\n=", standard, "Invalid base64 padding at offset 6"),
+            (
+                "aGVs\r\naGk",
+                standard,
+                "Missing base64 padding at offset 9",
+            ),
+            ("\n  aG=", standard, "Incomplete base64 padding at offset 5"),
+            (
+                "aGVs\naGk=",
+                Base64Format::StandardNoPad,
+                "Unexpected base64 padding at offset 8, the format has none",
+            ),
+            (
+                "\naGl=",
+                standard,
+                "Invalid last base64 character 'l' at offset 3, its unused bits aren't zero",
+            ),
+            (
+                "aGVs\n b",
+                standard,
+                "Truncated base64, a lone character at offset 6 ends the input",
+            ),
+        ] {
+            assert_eq!(decode(input, format), expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn test_detect_format() -> Result<()> {
+        let detect = |input: &[u8], complete| {
+            detect_format(input, complete).map(|(alphabet, padding)| (alphabet.as_str(), padding))
+        };
+        let standard = alphabet::STANDARD.as_str();
+        assert_eq!(
+            detect(b"aGk=\n", true)?,
+            (standard, DecodePaddingMode::RequireCanonical)
+        );
+        assert_eq!(
+            detect(b"aGk", true)?,
+            (standard, DecodePaddingMode::RequireNone)
+        );
+        assert_eq!(
+            detect(b"aGk", false)?,
+            (standard, DecodePaddingMode::Indifferent)
+        );
+        assert_eq!(detect(b"a-_b", true)?.0, alphabet::URL_SAFE.as_str());
+        assert_eq!(detect(b"ab./", true)?.0, alphabet::BCRYPT.as_str());
+        assert!(detect(b"ab.+", true).is_err());
+        assert!(detect(b"ab/_", true).is_err());
+
+        // padding is enforced once detected
+        let mut decoded = Vec::new();
+        assert!(process_decode(&mut "aGk".as_bytes(), &mut decoded, Base64Format::Auto).is_ok());
+        assert!(process_decode(&mut "aG=".as_bytes(), &mut decoded, Base64Format::Auto).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decode_bcrypt_hash() -> Result<()> {
+        let salt = *b"0123456789abcdef";
+        let hash = bcrypt::hash_with_salt("hunter2", 4, salt)?;
+        let hash = hash.format_for_version(bcrypt::Version::TwoB);
+        let mut decoded = Vec::new();
+        process_decode(
+            &mut format!("{hash}\n").as_bytes(),
+            &mut decoded,
+            Base64Format::Auto,
+        )?;
+        assert_eq!(decoded.len(), 16 + 23);
+        assert_eq!(decoded[..16], salt);
         Ok(())
     }
 }
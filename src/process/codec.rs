@@ -0,0 +1,373 @@
+//! binary-to-text codecs besides base64: hex, base32, base58 and base85
+use anyhow::{anyhow, bail, ensure, Result};
+use data_encoding::{Encoding, Specification, BASE32, BASE32_NOPAD, HEXLOWER_PERMISSIVE};
+
+use crate::{process_decode, process_encode, Base64Format, Codec};
+
+/// Crockford's base32 digits, no I, L, O or U
+const CROCKFORD: &str = "0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+/// dropped between hex bytes on decode, so the only ones allowed on encode besides whitespace
+const HEX_SEPARATORS: [char; 4] = ['-', ':', ',', '.'];
+/// ZeroMQ's base85 digits
+const Z85: &[u8; 85] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+/// how to encode
+#[derive(Debug, Clone)]
+pub struct CodecOptions {
+    pub codec: Codec,
+    /// upper case hex digits
+    pub upper: bool,
+    /// put between hex bytes, whitespace and `-`, `:`, `,` or `.`
+    pub separator: String,
+}
+
+/// encode bytes to text
+pub fn process_codec_encode(data: &[u8], options: &CodecOptions) -> Result<String> {
+    ensure!(
+        matches!(options.codec, Codec::Hex) || !options.upper && options.separator.is_empty(),
+        "--upper and --separator only apply to hex, not {}",
+        options.codec
+    );
+    ensure!(
+        options
+            .separator
+            .chars()
+            .all(|c| c.is_ascii_whitespace() || HEX_SEPARATORS.contains(&c)),
+        "Invalid hex separator {:?}, use whitespace, '-', ':', ',' or '.' so it can be decoded",
+        options.separator
+    );
+    let encoded = match options.codec {
+        Codec::Base64 => {
+            let mut encoded = Vec::new();
+            process_encode(&mut &data[..], &mut encoded, Base64Format::Standard)?;
+            String::from_utf8(encoded)?
+        }
+        Codec::Hex => data
+            .iter()
+            .map(|b| {
+                if options.upper {
+                    format!("{b:02X}")
+                } else {
+                    format!("{b:02x}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(&options.separator),
+        Codec::Base32 => BASE32.encode(data),
+        Codec::Base32Crockford => crockford()?.encode(data),
+        Codec::Base58 => bs58::encode(data).into_string(),
+        Codec::Base58Check => bs58::encode(data).with_check().into_string(),
+        Codec::Base85 => ascii85_encode(data),
+        Codec::Z85 => z85_encode(data)?,
+    };
+    Ok(encoded)
+}
+
+/// decode text to bytes, whitespace is ignored
+pub fn process_codec_decode(text: &str, codec: Codec) -> Result<Vec<u8>> {
+    let text = text
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect::<String>();
+    let decoded = match codec {
+        Codec::Base64 => {
+            let mut decoded = Vec::new();
+            process_decode(&mut text.as_bytes(), &mut decoded, Base64Format::Auto)?;
+            decoded
+        }
+        Codec::Hex => {
+            // separators like `:`, `-` and `,` between bytes are dropped, so are the 0x
+            // prefixes of every byte group, x isn't a hex digit so 0x can't be data
+            let text = text
+                .replace("0x", "")
+                .replace("0X", "")
+                .replace(HEX_SEPARATORS, "");
+            HEXLOWER_PERMISSIVE
+                .decode(text.as_bytes())
+                .map_err(|e| anyhow!("Invalid hex: {e}"))?
+        }
+        // padding is optional, plenty of producers such as otp secrets drop it
+        Codec::Base32 => BASE32_NOPAD
+            .decode(text.trim_end_matches('=').to_ascii_uppercase().as_bytes())
+            .map_err(|e| anyhow!("Invalid base32: {e}"))?,
+        Codec::Base32Crockford => crockford()?
+            .decode(text.as_bytes())
+            .map_err(|e| anyhow!("Invalid crockford base32: {e}"))?,
+        Codec::Base58 => bs58::decode(&text)
+            .into_vec()
+            .map_err(|e| anyhow!("Invalid base58: {e}"))?,
+        Codec::Base58Check => bs58::decode(&text)
+            .with_check(None)
+            .into_vec()
+            .map_err(|e| anyhow!("Invalid base58check: {e}"))?,
+        Codec::Base85 => ascii85_decode(&text)?,
+        Codec::Z85 => z85_decode(&text)?,
+    };
+    Ok(decoded)
+}
+
+/// Crockford's base32, decoding is case insensitive, reads O as 0, I and L as 1
+/// and skips hyphens
+fn crockford() -> Result<Encoding> {
+    let mut spec = Specification::new();
+    spec.symbols.push_str(CROCKFORD);
+    spec.translate.from.push_str("abcdefghjkmnpqrstvwxyzoOiIlL");
+    spec.translate.to.push_str("ABCDEFGHJKMNPQRSTVWXYZ001111");
+    spec.ignore.push('-');
+    Ok(spec.encoding()?)
+}
+
+/// Adobe's ascii85 between `<~` and `~>`, `z` stands for four zero bytes
+fn ascii85_encode(data: &[u8]) -> String {
+    let mut encoded = String::from("<~");
+    for chunk in data.chunks(4) {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        let mut value = u32::from_be_bytes(word);
+        if chunk.len() == 4 && value == 0 {
+            encoded.push('z');
+            continue;
+        }
+        let mut digits = [0u8; 5];
+        for digit in digits.iter_mut().rev() {
+            *digit = (value % 85) as u8 + b'!';
+            value /= 85;
+        }
+        // a partial chunk of n bytes needs only n + 1 digits
+        encoded.extend(digits[..chunk.len() + 1].iter().map(|&d| d as char));
+    }
+    encoded.push_str("~>");
+    encoded
+}
+
+fn ascii85_decode(text: &str) -> Result<Vec<u8>> {
+    let text = text.strip_prefix("<~").unwrap_or(text);
+    let text = text.strip_suffix("~>").unwrap_or(text);
+    let mut decoded = Vec::with_capacity(text.len() * 4 / 5);
+    let mut group = Vec::with_capacity(5);
+    for (offset, c) in text.bytes().enumerate() {
+        match c {
+            b'z' if group.is_empty() => decoded.extend_from_slice(&[0; 4]),
+            b'!'..=b'u' => {
+                group.push(c - b'!');
+                if group.len() == 5 {
+                    decoded.extend_from_slice(&base85_word(&group)?);
+                    group.clear();
+                }
+            }
+            _ => bail!(
+                "Invalid base85 character {:?} at offset {offset}",
+                c as char
+            ),
+        }
+    }
+    match group.len() {
+        0 => {}
+        1 => bail!("Invalid base85: a final group needs at least two characters"),
+        n => {
+            // pad with the highest digit, then drop the padding bytes again
+            group.resize(5, 84);
+            decoded.extend_from_slice(&base85_word(&group)?[..n - 1]);
+        }
+    }
+    Ok(decoded)
+}
+
+/// ZeroMQ's Z85, the input must be a multiple of four bytes
+fn z85_encode(data: &[u8]) -> Result<String> {
+    ensure!(
+        data.len().is_multiple_of(4),
+        "Z85 encodes multiples of 4 bytes, got {} bytes",
+        data.len()
+    );
+    let mut encoded = String::with_capacity(data.len() * 5 / 4);
+    for chunk in data.chunks(4) {
+        let mut value = u32::from_be_bytes(chunk.try_into()?);
+        let mut digits = [0u8; 5];
+        for digit in digits.iter_mut().rev() {
+            *digit = Z85[(value % 85) as usize];
+            value /= 85;
+        }
+        encoded.extend(digits.iter().map(|&d| d as char));
+    }
+    Ok(encoded)
+}
+
+fn z85_decode(text: &str) -> Result<Vec<u8>> {
+    ensure!(
+        text.len().is_multiple_of(5),
+        "Z85 decodes multiples of 5 characters, got {}",
+        text.len()
+    );
+    let mut decoded = Vec::with_capacity(text.len() * 4 / 5);
+    for (i, chunk) in text.as_bytes().chunks(5).enumerate() {
+        let digits = chunk
+            .iter()
+            .enumerate()
+            .map(|(j, c)| {
+                Z85.iter()
+                    .position(|z| z == c)
+                    .map(|d| d as u8)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "Invalid z85 character {:?} at offset {}",
+                            *c as char,
+                            i * 5 + j
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        decoded.extend_from_slice(&base85_word(&digits)?);
+    }
+    Ok(decoded)
+}
+
+/// five base85 digits to four bytes
+fn base85_word(digits: &[u8]) -> Result<[u8; 4]> {
+    let value = digits
+        .iter()
+        .try_fold(0u32, |acc, &d| acc.checked_mul(85)?.checked_add(d as u32))
+        .ok_or_else(|| anyhow!("Invalid base85: group overflows 32 bits"))?;
+    Ok(value.to_be_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(codec: Codec) -> CodecOptions {
+        CodecOptions {
+            codec,
+            upper: false,
+            separator: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_process_codec_encode() -> Result<()> {
+        let hello = b"Hello World!";
+        for (codec, expected) in [
+            (Codec::Hex, "48656c6c6f20576f726c6421"),
+            (Codec::Base32, "JBSWY3DPEBLW64TMMQQQ===="),
+            (Codec::Base32Crockford, "91JPRV3F41BPYWKCCGGG"),
+            (Codec::Base58, "2NEpo7TZRRrLZSi2U"),
+            (Codec::Base85, "<~87cURD]i,\"Ebo80~>"),
+            (Codec::Z85, "nm=QNzY&b1A+]nf"),
+        ] {
+            assert_eq!(process_codec_encode(hello, &options(codec))?, expected);
+            assert_eq!(process_codec_decode(expected, codec)?, hello);
+        }
+
+        // the Z85 spec's test vector
+        let data = [0x86, 0x4F, 0xD2, 0x6F, 0xB5, 0x59, 0xF7, 0x5B];
+        assert_eq!(
+            process_codec_encode(&data, &options(Codec::Z85))?,
+            "HelloWorld"
+        );
+
+        let options = CodecOptions {
+            codec: Codec::Hex,
+            upper: true,
+            separator: ":".to_string(),
+        };
+        assert_eq!(
+            process_codec_encode(&[0xde, 0xad, 0xbe, 0xef], &options)?,
+            "DE:AD:BE:EF"
+        );
+
+        // every separator accepted on encode decodes again
+        for separator in [" ", ":", "-", ", ", ".", "\n"] {
+            let options = CodecOptions {
+                separator: separator.to_string(),
+                ..options.clone()
+            };
+            let encoded = process_codec_encode(&[0xde, 0xad, 0xbe, 0xef], &options)?;
+            assert_eq!(
+                process_codec_decode(&encoded, Codec::Hex)?,
+                [0xde, 0xad, 0xbe, 0xef],
+                "{separator:?}"
+            );
+        }
+        for separator in ["|", "ab", "0x", ";"] {
+            let options = CodecOptions {
+                separator: separator.to_string(),
+                ..options.clone()
+            };
+            assert!(process_codec_encode(&[0xde, 0xad], &options).is_err());
+        }
+
+        // hex only options aren't silently dropped
+        for (upper, separator) in [(true, ""), (false, ":")] {
+            let options = CodecOptions {
+                codec: Codec::Base32,
+                upper,
+                separator: separator.to_string(),
+            };
+            assert!(process_codec_encode(b"hi", &options).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_codec_decode() -> Result<()> {
+        let data = (0..=255u8).collect::<Vec<_>>();
+        for codec in [
+            Codec::Base64,
+            Codec::Hex,
+            Codec::Base32,
+            Codec::Base32Crockford,
+            Codec::Base58,
+            Codec::Base58Check,
+            Codec::Base85,
+            Codec::Z85,
+        ] {
+            let encoded = process_codec_encode(&data, &options(codec))?;
+            assert_eq!(process_codec_decode(&encoded, codec)?, data, "{codec}");
+        }
+
+        assert_eq!(
+            process_codec_decode("DE:ad be-EF", Codec::Hex)?,
+            [0xde, 0xad, 0xbe, 0xef]
+        );
+        for hex in [
+            "0xDE 0xAD 0xBE 0xEF",
+            "0xde,0xad,0xbe,0xef",
+            "0XDEAD 0xbeef",
+        ] {
+            assert_eq!(
+                process_codec_decode(hex, Codec::Hex)?,
+                [0xde, 0xad, 0xbe, 0xef],
+                "{hex}"
+            );
+        }
+        for base32 in [
+            "JBSWY3DPEBLW64TMMQQQ====",
+            "JBSWY3DPEBLW64TMMQQQ",
+            "jbswy3dpeblw64tmmqqq",
+        ] {
+            assert_eq!(
+                process_codec_decode(base32, Codec::Base32)?,
+                b"Hello World!"
+            );
+        }
+        assert!(process_codec_decode("JBSWY3D", Codec::Base32).is_err());
+        assert_eq!(
+            process_codec_decode("91jprv3f-41bpywkc-cggg", Codec::Base32Crockford)?,
+            b"Hello World!"
+        );
+        assert_eq!(
+            process_codec_decode("<~z!!~>", Codec::Base85)?,
+            [0, 0, 0, 0, 0]
+        );
+
+        let encoded = process_codec_encode(b"key", &options(Codec::Base58Check))?;
+        let mut corrupted = encoded.into_bytes();
+        corrupted[0] = if corrupted[0] == b'2' { b'3' } else { b'2' };
+        assert!(
+            process_codec_decode(std::str::from_utf8(&corrupted)?, Codec::Base58Check).is_err()
+        );
+        assert!(process_codec_decode("abc", Codec::Z85).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+//! Write csv records as columnar arrow ipc / parquet files
+use std::sync::Arc;
+
+use anyhow::{anyhow, ensure, Result};
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_ipc::{writer::FileWriter, writer::IpcWriteOptions, CompressionType};
+use arrow_schema::{DataType, Field, Schema};
+use csv::StringRecord;
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression, GzipLevel, ZstdLevel},
+    file::properties::WriterProperties,
+};
+
+use super::csv_convert::CsvWriteOptions;
+use crate::CsvCompression;
+
+/// write the records as a parquet file, split into row groups
+pub(super) fn to_parquet(
+    headers: &StringRecord,
+    records: &[Vec<String>],
+    options: &CsvWriteOptions,
+) -> Result<Vec<u8>> {
+    ensure!(
+        options.row_group_size > 0,
+        "Row group size must be positive"
+    );
+    let batch = to_record_batch(headers, records)?;
+
+    let compression = match options.compression {
+        CsvCompression::None => Compression::UNCOMPRESSED,
+        CsvCompression::Snappy => Compression::SNAPPY,
+        CsvCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+        CsvCompression::Lz4 => Compression::LZ4_RAW,
+        CsvCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+    };
+    let props = WriterProperties::builder()
+        .set_compression(compression)
+        .set_max_row_group_size(options.row_group_size)
+        .build();
+
+    let mut buf = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buf, batch.schema(), Some(props))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(buf)
+}
+
+/// write the records as an arrow ipc file, split into record batches
+pub(super) fn to_arrow_ipc(
+    headers: &StringRecord,
+    records: &[Vec<String>],
+    options: &CsvWriteOptions,
+) -> Result<Vec<u8>> {
+    ensure!(
+        options.row_group_size > 0,
+        "Row group size must be positive"
+    );
+    let batch = to_record_batch(headers, records)?;
+
+    let compression = match options.compression {
+        CsvCompression::None => None,
+        CsvCompression::Lz4 => Some(CompressionType::LZ4_FRAME),
+        CsvCompression::Zstd => Some(CompressionType::ZSTD),
+        other => return Err(anyhow!("Arrow ipc doesn't support {other} compression")),
+    };
+    let write_options = IpcWriteOptions::default().try_with_compression(compression)?;
+
+    let mut writer = FileWriter::try_new_with_options(Vec::new(), &batch.schema(), write_options)?;
+    let rows = batch.num_rows();
+    for offset in (0..rows).step_by(options.row_group_size) {
+        let len = options.row_group_size.min(rows - offset);
+        writer.write(&batch.slice(offset, len))?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+/// build a single record batch, inferring the type of every column
+fn to_record_batch(headers: &StringRecord, records: &[Vec<String>]) -> Result<RecordBatch> {
+    let mut fields = Vec::with_capacity(headers.len());
+    let mut columns = Vec::with_capacity(headers.len());
+
+    for (i, header) in headers.iter().enumerate() {
+        let values = records.iter().map(|r| r[i].as_str()).collect::<Vec<_>>();
+        let data_type = infer_type(&values);
+        // empty cells become nulls in typed columns
+        let column: ArrayRef = match data_type {
+            DataType::Int64 => Arc::new(Int64Array::from_iter(
+                values.iter().map(|v| v.parse::<i64>().ok()),
+            )),
+            DataType::Float64 => Arc::new(Float64Array::from_iter(
+                values.iter().map(|v| v.parse::<f64>().ok()),
+            )),
+            DataType::Boolean => Arc::new(BooleanArray::from_iter(
+                values.iter().map(|v| parse_bool(v)),
+            )),
+            _ => Arc::new(StringArray::from(values)),
+        };
+        fields.push(Field::new(header, data_type, true));
+        columns.push(column);
+    }
+
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
+
+/// the narrowest type every non-empty value of the column parses as
+fn infer_type(values: &[&str]) -> DataType {
+    let mut non_empty = values.iter().filter(|v| !v.is_empty()).peekable();
+    if non_empty.peek().is_none() {
+        return DataType::Utf8;
+    }
+
+    let non_empty = non_empty.collect::<Vec<_>>();
+    // zip codes and ids like 00501 would lose their zeros as numbers
+    if non_empty.iter().any(|v| has_leading_zero(v)) {
+        DataType::Utf8
+    } else if non_empty.iter().all(|v| v.parse::<i64>().is_ok()) {
+        DataType::Int64
+    } else if non_empty
+        .iter()
+        .all(|v| v.parse::<f64>().is_ok_and(f64::is_finite))
+    {
+        DataType::Float64
+    } else if non_empty.iter().all(|v| parse_bool(v).is_some()) {
+        DataType::Boolean
+    } else {
+        DataType::Utf8
+    }
+}
+
+/// a zero followed by more digits, `0` itself and `0.5` are fine
+fn has_leading_zero(value: &str) -> bool {
+    let digits = value.trim_start_matches(['-', '+']).as_bytes();
+    digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit()
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    if value.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if value.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{process::csv_convert::read_records, CsvAnonymizer};
+    use arrow_ipc::reader::FileReader;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_infer_type() {
+        assert_eq!(infer_type(&["1", "", "37"]), DataType::Int64);
+        assert_eq!(infer_type(&["1", "2.5"]), DataType::Float64);
+        assert_eq!(infer_type(&["true", "FALSE"]), DataType::Boolean);
+        assert_eq!(infer_type(&["1", "Poland"]), DataType::Utf8);
+        assert_eq!(infer_type(&["", ""]), DataType::Utf8);
+
+        // leading zeros and non-finite floats stay text
+        assert_eq!(infer_type(&["01234", "5"]), DataType::Utf8);
+        assert_eq!(infer_type(&["00501"]), DataType::Utf8);
+        assert_eq!(infer_type(&["-007"]), DataType::Utf8);
+        assert_eq!(infer_type(&["00.5"]), DataType::Utf8);
+        assert_eq!(infer_type(&["0", "-0", "10"]), DataType::Int64);
+        assert_eq!(infer_type(&["0.5", "0", "1e3"]), DataType::Float64);
+        assert_eq!(infer_type(&["1.5", "NaN"]), DataType::Utf8);
+        assert_eq!(infer_type(&["inf", "2"]), DataType::Utf8);
+    }
+
+    #[test]
+    fn test_to_columnar() -> Result<()> {
+        let (headers, records) = read_records("fixtures/juventus.csv", &CsvAnonymizer::default())?;
+        let options = CsvWriteOptions {
+            compression: CsvCompression::Zstd,
+            row_group_size: 10,
+            ..Default::default()
+        };
+
+        let parquet = to_parquet(&headers, &records, &options)?;
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(parquet))?;
+        assert_eq!(reader.metadata().num_row_groups(), 3);
+        let schema = reader.schema().clone();
+        assert_eq!(schema.field(0).name(), "Name");
+        assert_eq!(schema.field(4).data_type(), &DataType::Int64);
+
+        let ipc = to_arrow_ipc(&headers, &records, &options)?;
+        let reader = FileReader::try_new(Cursor::new(ipc), None)?;
+        assert_eq!(reader.num_batches(), 3);
+        let rows = reader
+            .map(|b| b.map(|b| b.num_rows()))
+            .sum::<Result<usize, _>>()?;
+        assert_eq!(rows, records.len());
+
+        let options = CsvWriteOptions {
+            compression: CsvCompression::Snappy,
+            ..Default::default()
+        };
+        assert!(to_arrow_ipc(&headers, &records, &options).is_err());
+        Ok(())
+    }
+}
@@ -1,9 +1,15 @@
 //! Process the csv file and delete the corresponding format
-use anyhow::Result;
-use csv::Reader;
+use anyhow::{anyhow, ensure, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use csv::{Reader, StringRecord};
 use serde_json::Value;
+use std::fmt::Write as _;
 
-use crate::cli::OutputFormat;
+use super::{
+    csv_columnar::{to_arrow_ipc, to_parquet},
+    text::{Blake3, TextSigner},
+};
+use crate::{cli::OutputFormat, get_content, CsvCompression};
 
 // use serde::{Deserialize, Serialize};
 // #[derive(Debug, Serialize, Deserialize)]
@@ -21,8 +27,116 @@ use crate::cli::OutputFormat;
 //     kit: u8,
 // }
 
-/// Process the csv file and delete the corresponding format
-pub async fn process_csv(input: &str, output: String, format: OutputFormat) -> Result<()> {
+/// replacement for masked values
+const MASK: &str = "****";
+
+/// format specific output settings
+#[derive(Debug, Clone)]
+pub struct CsvWriteOptions {
+    /// xml root element name
+    pub xml_root: String,
+    /// xml element name of every record
+    pub xml_row: String,
+    /// compression codec of columnar output
+    pub compression: CsvCompression,
+    /// rows per parquet row group / arrow record batch
+    pub row_group_size: usize,
+}
+
+impl Default for CsvWriteOptions {
+    fn default() -> Self {
+        Self {
+            xml_root: "rows".to_string(),
+            xml_row: "row".to_string(),
+            compression: CsvCompression::None,
+            row_group_size: 1024 * 1024,
+        }
+    }
+}
+
+/// mask or pseudonymize sensitive columns before they are written out
+#[derive(Default)]
+pub struct CsvAnonymizer {
+    mask: Vec<String>,
+    pseudonymize: Vec<(String, Blake3)>,
+}
+
+/// what to do with the value of a single column
+enum ColumnRule<'a> {
+    Keep,
+    Mask,
+    Pseudonymize(&'a Blake3),
+}
+
+impl CsvAnonymizer {
+    /// redact every value of the column
+    pub fn mask(mut self, column: impl Into<String>) -> Self {
+        self.mask.push(column.into());
+        self
+    }
+
+    /// replace every value of the column with a keyed blake3 token
+    pub fn pseudonymize(
+        mut self,
+        column: impl Into<String>,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Self> {
+        self.pseudonymize
+            .push((column.into(), Blake3::try_new(key)?));
+        Ok(self)
+    }
+
+    /// match the configured columns against the csv headers
+    fn rules(&self, headers: &StringRecord) -> Result<Vec<ColumnRule<'_>>> {
+        let mut rules = headers.iter().map(|_| ColumnRule::Keep).collect::<Vec<_>>();
+        let position = |column: &str| {
+            headers
+                .iter()
+                .position(|h| h == column)
+                .ok_or_else(|| anyhow!("Column {column:?} not found in csv headers"))
+        };
+
+        let columns = self
+            .mask
+            .iter()
+            .map(|column| (column, ColumnRule::Mask))
+            .chain(
+                self.pseudonymize
+                    .iter()
+                    .map(|(column, signer)| (column, ColumnRule::Pseudonymize(signer))),
+            );
+        for (column, rule) in columns {
+            let slot = &mut rules[position(column)?];
+            ensure!(
+                matches!(slot, ColumnRule::Keep),
+                "Column {column:?} is given to more than one of --mask and --pseudonymize"
+            );
+            *slot = rule;
+        }
+
+        Ok(rules)
+    }
+}
+
+impl ColumnRule<'_> {
+    fn apply(&self, value: &str) -> Result<String> {
+        let value = match self {
+            ColumnRule::Keep => value.to_string(),
+            ColumnRule::Mask => MASK.to_string(),
+            // the same value and key always give the same token, so joins still work
+            ColumnRule::Pseudonymize(signer) => {
+                URL_SAFE_NO_PAD.encode(signer.sign(&mut value.as_bytes())?)
+            }
+        };
+        Ok(value)
+    }
+}
+
+/// read the csv headers and records, applying the anonymizer to every record
+pub(super) fn read_records(
+    input: &str,
+    anonymizer: &CsvAnonymizer,
+) -> Result<(StringRecord, Vec<Vec<String>>)> {
     // use csv reader to read csv file
     let mut reader = Reader::from_path(input)?;
 
@@ -31,20 +145,67 @@ pub async fn process_csv(input: &str, output: String, format: OutputFormat) -> R
 
     // get csv file headers
     let headers = reader.headers()?.clone();
+    let rules = anonymizer.rules(&headers)?;
 
     for result in reader.records() {
         // get csv file event line
         let record = result?;
-        // match the header to the field, collect to a vector
-        let json_value = headers.iter().zip(record.iter()).collect::<Value>();
-        ret.push(json_value);
+        let values = rules
+            .iter()
+            .zip(record.iter())
+            .map(|(rule, value)| rule.apply(value))
+            .collect::<Result<Vec<_>>>()?;
+        ret.push(values);
     }
 
-    // converts the result to the corresponding format
+    Ok((headers, ret))
+}
+
+/// match the header to the field of every record, collect to a vector
+fn to_values(headers: &StringRecord, records: &[Vec<String>]) -> Vec<Value> {
+    records
+        .iter()
+        .map(|values| {
+            headers
+                .iter()
+                .zip(values.iter().map(String::as_str))
+                .collect::<Value>()
+        })
+        .collect()
+}
+
+/// read the csv file into json values, applying the anonymizer to every record
+pub fn read_csv(input: &str, anonymizer: &CsvAnonymizer) -> Result<Vec<Value>> {
+    let (headers, records) = read_records(input, anonymizer)?;
+    Ok(to_values(&headers, &records))
+}
+
+/// Process the csv file and delete the corresponding format
+pub async fn process_csv(
+    input: &str,
+    output: String,
+    format: OutputFormat,
+    anonymizer: &CsvAnonymizer,
+    options: &CsvWriteOptions,
+) -> Result<()> {
+    let (headers, records) = read_records(input, anonymizer)?;
+    let ret = || to_values(&headers, &records);
+
+    // converts the result to the corresponding format, binary formats are not utf-8 text
     let content = match format {
-        OutputFormat::Json => serde_json::to_string_pretty(&ret)?,
-        OutputFormat::Yaml => serde_yaml::to_string(&ret)?,
-        OutputFormat::Toml => "This format is currently not supported".to_string(),
+        OutputFormat::Json => serde_json::to_string_pretty(&ret())?.into_bytes(),
+        OutputFormat::Yaml => serde_yaml::to_string(&ret())?.into_bytes(),
+        OutputFormat::Toml => b"This format is currently not supported".to_vec(),
+        OutputFormat::Xml => to_xml(&headers, &records, options)?.into_bytes(),
+        OutputFormat::MsgPack => rmp_serde::to_vec_named(&ret())?,
+        OutputFormat::Cbor => {
+            let mut buf = Vec::new();
+            ciborium::into_writer(&ret(), &mut buf)?;
+            buf
+        }
+        // columnar formats keep the header order and infer the column types
+        OutputFormat::Parquet => to_parquet(&headers, &records, options)?,
+        OutputFormat::Arrow => to_arrow_ipc(&headers, &records, options)?,
     };
 
     // output the result to the corresponding file
@@ -53,27 +214,183 @@ pub async fn process_csv(input: &str, output: String, format: OutputFormat) -> R
     Ok(())
 }
 
+/// decode a msgpack/cbor file produced by `process_csv` back into json for inspection
+pub fn process_csv_decode(input: &str, format: OutputFormat) -> Result<String> {
+    let content = get_content(input)?;
+    let ret: Value = match format {
+        OutputFormat::MsgPack => rmp_serde::from_slice(&content)?,
+        OutputFormat::Cbor => ciborium::from_reader(content.as_slice())?,
+        _ => return Err(anyhow!("Only msgpack and cbor output can be decoded")),
+    };
+    Ok(serde_json::to_string_pretty(&ret)?)
+}
+
+/// serialize the records as `<root><row><Header>value</Header></row></root>`,
+/// the elements keep the csv column order
+fn to_xml(
+    headers: &StringRecord,
+    records: &[Vec<String>],
+    options: &CsvWriteOptions,
+) -> Result<String> {
+    let root = &options.xml_root;
+    let row = &options.xml_row;
+    let names = headers.iter().map(sanitize_xml_name).collect::<Vec<_>>();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(xml, "<{root}>")?;
+
+    for record in records {
+        writeln!(xml, "  <{row}>")?;
+        for (name, value) in names.iter().zip(record) {
+            writeln!(xml, "    <{name}>{}</{name}>", escape_xml(value))?;
+        }
+        writeln!(xml, "  </{row}>")?;
+    }
+
+    writeln!(xml, "</{root}>")?;
+    Ok(xml)
+}
+
+/// whether the name can be used as an xml element name as is
+pub fn is_xml_name(name: &str) -> bool {
+    sanitize_xml_name(name) == name
+}
+
+/// turn a csv header into a valid xml element name
+fn sanitize_xml_name(name: &str) -> String {
+    let mut ret = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    // names must start with a letter or underscore, and `xml` is a reserved prefix
+    let invalid_start = !ret.starts_with(|c: char| c.is_alphabetic() || c == '_');
+    if invalid_start || ret.to_ascii_lowercase().starts_with("xml") {
+        ret.insert(0, '_');
+    }
+    ret
+}
+
+/// escape xml markup characters and drop characters xml 1.0 can't carry
+fn escape_xml(value: &str) -> String {
+    let mut ret = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '"' => ret.push_str("&quot;"),
+            '\'' => ret.push_str("&apos;"),
+            '\t' | '\n' | '\r' => ret.push(c),
+            c if c.is_control() => ret.push(char::REPLACEMENT_CHARACTER),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use anyhow::Result;
+    const KEY: &[u8] = include_bytes!("../../fixtures/blake3.txt");
 
     #[tokio::test]
     async fn test_process_csv() -> Result<()> {
-        process_csv(
-            "fixtures/juventus.csv",
-            "output.json".into(),
-            OutputFormat::Json,
-        )
-        .await?;
-
-        process_csv(
-            "fixtures/juventus.csv",
-            "output.yaml".into(),
-            OutputFormat::Yaml,
-        )
-        .await?;
+        let anonymizer = CsvAnonymizer::default();
+        for format in [OutputFormat::Json, OutputFormat::Yaml] {
+            let output = std::env::temp_dir().join(format!("rcli-test-csv.{format}"));
+            process_csv(
+                "fixtures/juventus.csv",
+                output.display().to_string(),
+                format,
+                &anonymizer,
+                &CsvWriteOptions::default(),
+            )
+            .await?;
+            std::fs::remove_file(output)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_csv_anonymized() -> Result<()> {
+        let anonymizer = CsvAnonymizer::default()
+            .mask("Name")
+            .pseudonymize("DOB", KEY)?;
+        let first = read_csv("fixtures/juventus.csv", &anonymizer)?;
+        let second = read_csv("fixtures/juventus.csv", &anonymizer)?;
+
+        assert_eq!(first[0]["Name"], MASK);
+        assert_eq!(first[0]["Position"], "Goalkeeper");
+        assert_ne!(first[0]["DOB"], "Apr 18, 1990 (29)");
+        // pseudonyms are stable between runs
+        assert_eq!(first[0]["DOB"], second[0]["DOB"]);
+
+        let anonymizer = CsvAnonymizer::default().mask("Unknown");
+        assert!(read_csv("fixtures/juventus.csv", &anonymizer).is_err());
+
+        // a column can't be both masked and pseudonymized
+        let anonymizer = CsvAnonymizer::default()
+            .mask("Name")
+            .pseudonymize("Name", KEY)?;
+        assert!(read_csv("fixtures/juventus.csv", &anonymizer).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_xml() -> Result<()> {
+        let (headers, records) = read_records("fixtures/juventus.csv", &CsvAnonymizer::default())?;
+        let xml = to_xml(&headers, &records, &CsvWriteOptions::default())?;
+        assert!(xml.contains("<rows>\n  <row>\n"));
+        assert!(xml.contains("<Kit_Number>1</Kit_Number>"));
+
+        // elements follow the csv columns, not alphabetical order
+        let row = xml.split("</row>").next().expect("a row");
+        let positions = [
+            "<Name>",
+            "<Position>",
+            "<DOB>",
+            "<Nationality>",
+            "<Kit_Number>",
+        ]
+        .map(|tag| row.find(tag).expect(tag));
+        assert!(positions.is_sorted(), "{row}");
+
+        assert_eq!(sanitize_xml_name("Kit Number"), "Kit_Number");
+        assert_eq!(sanitize_xml_name("1st"), "_1st");
+        assert_eq!(sanitize_xml_name("xmlns"), "_xmlns");
+        assert_eq!(escape_xml("a < b & \"c\""), "a &lt; b &amp; &quot;c&quot;");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_csv_binary() -> Result<()> {
+        let anonymizer = CsvAnonymizer::default();
+        let expected =
+            serde_json::to_string_pretty(&read_csv("fixtures/juventus.csv", &anonymizer)?)?;
+
+        for format in [OutputFormat::MsgPack, OutputFormat::Cbor] {
+            let output = std::env::temp_dir().join(format!("rcli-test-csv.{format}"));
+            let output = output.display().to_string();
+            process_csv(
+                "fixtures/juventus.csv",
+                output.clone(),
+                format,
+                &anonymizer,
+                &CsvWriteOptions::default(),
+            )
+            .await?;
+            assert_eq!(process_csv_decode(&output, format)?, expected);
+            std::fs::remove_file(output)?;
+        }
 
+        assert!(process_csv_decode("fixtures/juventus.csv", OutputFormat::Json).is_err());
         Ok(())
     }
 }
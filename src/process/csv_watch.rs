@@ -0,0 +1,205 @@
+//! Re-run the csv conversion whenever the input file changes
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebounceEventResult};
+use tokio::sync::mpsc;
+
+use super::csv_convert::{process_csv, CsvAnonymizer, CsvWriteOptions};
+use crate::cli::OutputFormat;
+
+/// watch the input file and convert it again after every burst of writes
+pub async fn process_csv_watch(
+    input: &str,
+    output: String,
+    format: OutputFormat,
+    anonymizer: &CsvAnonymizer,
+    options: &CsvWriteOptions,
+    debounce: Duration,
+) -> Result<()> {
+    // editors and spreadsheets often replace the file, so watch its directory instead
+    let path = std::fs::canonicalize(input)?;
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("Input file has no parent directory"))?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut debouncer = new_debouncer(debounce, move |res: DebounceEventResult| {
+        let _ = tx.send(res);
+    })?;
+    debouncer
+        .watcher()
+        .watch(dir, RecursiveMode::NonRecursive)?;
+
+    println!("Watching {} for changes", input.bright_purple());
+    let mut watch = InputWatch::new(path);
+    convert(input, &output, format, anonymizer, options).await;
+
+    while let Some(res) = rx.recv().await {
+        if watch.changed(res) {
+            convert(input, &output, format, anonymizer, options).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// size and modification time, together they catch writes within the mtime granularity
+type Fingerprint = (u64, SystemTime);
+
+/// the watched input and what it looked like at the last conversion
+struct InputWatch {
+    path: PathBuf,
+    last: Option<Fingerprint>,
+}
+
+impl InputWatch {
+    fn new(path: PathBuf) -> Self {
+        let last = fingerprint(&path);
+        Self { path, last }
+    }
+
+    /// whether a batch of debounced events changed the input, watch errors are reported
+    /// and skipped so a single hiccup doesn't end the watch
+    fn changed(&mut self, res: DebounceEventResult) -> bool {
+        match res {
+            Ok(events) if events.iter().any(|e| e.path == self.path) => {
+                // reading the input also raises events, only convert on actual writes
+                let current = fingerprint(&self.path);
+                if current.is_some() && current != self.last {
+                    self.last = current;
+                    return true;
+                }
+                false
+            }
+            Ok(_) => false,
+            Err(e) => {
+                eprintln!("{} {e}", "⚠ Watch error:".bright_red());
+                false
+            }
+        }
+    }
+}
+
+fn fingerprint(path: &Path) -> Option<Fingerprint> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+/// run a single conversion, reporting errors instead of returning them
+async fn convert(
+    input: &str,
+    output: &str,
+    format: OutputFormat,
+    anonymizer: &CsvAnonymizer,
+    options: &CsvWriteOptions,
+) {
+    match process_csv(input, output.to_string(), format, anonymizer, options).await {
+        Ok(()) => println!("{} {input} -> {output}", "✓ Converted".bright_green()),
+        Err(e) => eprintln!("{} {e}", "⚠ Conversion failed:".bright_red()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, fs::File, sync::mpsc as std_mpsc};
+
+    use notify_debouncer_mini::{notify, DebouncedEvent, DebouncedEventKind};
+
+    use super::*;
+
+    fn event(path: &Path) -> DebounceEventResult {
+        Ok(vec![DebouncedEvent::new(
+            path.to_path_buf(),
+            DebouncedEventKind::Any,
+        )])
+    }
+
+    #[test]
+    fn test_input_watch_changed() -> Result<()> {
+        let path = std::fs::canonicalize(std::env::temp_dir())?.join("rcli-test-watch.csv");
+        fs::write(&path, "a,b\n1,2\n")?;
+        let mtime = fs::metadata(&path)?.modified()?;
+        let mut watch = InputWatch::new(path.clone());
+
+        // reads and other files in the directory don't count
+        assert!(!watch.changed(event(&path)));
+        assert!(!watch.changed(event(Path::new("Cargo.toml"))));
+
+        // a write within the mtime granularity still changes the size
+        fs::write(&path, "a,b\n1,2\n3,4\n")?;
+        File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(mtime)?;
+        assert!(watch.changed(event(&path)));
+        assert!(!watch.changed(event(&path)));
+
+        // a same size write changes the mtime
+        fs::write(&path, "a,b\n5,6\n7,8\n")?;
+        File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(mtime + Duration::from_secs(1))?;
+        assert!(watch.changed(event(&path)));
+
+        // errors are reported and the watch goes on
+        assert!(!watch.changed(Err(notify::Error::generic("watch failed"))));
+        fs::write(&path, "a,b\n")?;
+        assert!(watch.changed(event(&path)));
+
+        // a removed file is no change, the next write is
+        fs::remove_file(&path)?;
+        assert!(!watch.changed(event(&path)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_input_watch_debounce() -> Result<()> {
+        let path = std::fs::canonicalize(std::env::temp_dir())?.join("rcli-test-debounce.csv");
+        fs::write(&path, "a,b\n")?;
+        let (tx, rx) = std_mpsc::channel();
+        let mut debouncer = new_debouncer(Duration::from_millis(300), move |res| {
+            let _ = tx.send(res);
+        })?;
+        debouncer
+            .watcher()
+            .watch(&path, RecursiveMode::NonRecursive)?;
+        let mut watch = InputWatch::new(path.clone());
+
+        // a burst of writes, like an editor saving in several steps
+        for i in 0..5 {
+            fs::write(&path, format!("a,b\n{i},{i}{i}\n"))?;
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let mut conversions = 0;
+        while let Ok(res) = rx.recv_timeout(Duration::from_secs(2)) {
+            conversions += watch.changed(res) as usize;
+        }
+        assert_eq!(conversions, 1);
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_convert_reports_errors() {
+        // a failing conversion is printed, not returned, so the watch keeps running
+        let options = CsvWriteOptions::default();
+        let anonymizer = CsvAnonymizer::default();
+        let output = std::env::temp_dir().join("rcli-test-missing.json");
+        convert(
+            "fixtures/missing.csv",
+            &output.display().to_string(),
+            OutputFormat::Json,
+            &anonymizer,
+            &options,
+        )
+        .await;
+        assert!(!output.exists());
+    }
+}
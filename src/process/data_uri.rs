@@ -0,0 +1,285 @@
+//! RFC 2397 data uris
+use std::{
+    io::{BufRead, Cursor, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use percent_encoding::percent_decode;
+
+use crate::{process_decode, process_encode, write_file_atomic, Base64Format};
+
+/// bytes looked at to sniff the mime type
+const SNIFF_LEN: u64 = 512;
+/// longest `data:<mime>;<params>;base64,` header accepted
+const MAX_HEADER_LEN: usize = 1024;
+
+/// file extensions and their mime types, the first extension of a type is preferred
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/x-icon"),
+    ("bmp", "image/bmp"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+    ("wav", "audio/wav"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("wasm", "application/wasm"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("css", "text/css"),
+    ("js", "text/javascript"),
+    ("mjs", "text/javascript"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("csv", "text/csv"),
+    ("txt", "text/plain"),
+];
+
+/// the parsed `data:<mime>[;base64],` header of a data uri
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataUri {
+    /// mime type with its parameters, `text/plain;charset=US-ASCII` when omitted
+    pub mime: String,
+    pub base64: bool,
+}
+
+/// guess the mime type from magic bytes, then the file extension, then whether it's text
+pub fn sniff_mime(prefix: &[u8], path: Option<&Path>) -> &'static str {
+    if let Some(mime) = sniff_magic(prefix) {
+        return mime;
+    }
+    let ext = path
+        .and_then(|path| path.extension())
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    if let Some(&(_, mime)) = MIME_TYPES.iter().find(|(e, _)| Some(*e) == ext.as_deref()) {
+        return mime;
+    }
+    match std::str::from_utf8(prefix) {
+        Ok(_) => "text/plain",
+        // a multi-byte character cut off at the end of the prefix is still text
+        Err(e) if e.error_len().is_none() => "text/plain",
+        Err(_) => "application/octet-stream",
+    }
+}
+
+fn sniff_magic(prefix: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, magic: &[u8]| prefix.get(offset..offset + magic.len()) == Some(magic);
+    let mime = match prefix {
+        [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n', ..] => "image/png",
+        [0xff, 0xd8, 0xff, ..] => "image/jpeg",
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => "image/gif",
+        [b'R', b'I', b'F', b'F', ..] if at(8, b"WEBP") => "image/webp",
+        [b'R', b'I', b'F', b'F', ..] if at(8, b"WAVE") => "audio/wav",
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] if at(8, b"avif") => "image/avif",
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => "video/mp4",
+        [0, 0, 1, 0, ..] => "image/x-icon",
+        [b'w', b'O', b'F', b'F', ..] => "font/woff",
+        [b'w', b'O', b'F', b'2', ..] => "font/woff2",
+        [0, 1, 0, 0, 0, ..] => "font/ttf",
+        [b'O', b'T', b'T', b'O', ..] => "font/otf",
+        [b'I', b'D', b'3', ..] => "audio/mpeg",
+        [b'O', b'g', b'g', b'S', ..] => "audio/ogg",
+        [0x1a, 0x45, 0xdf, 0xa3, ..] => "video/webm",
+        [b'%', b'P', b'D', b'F', b'-', ..] => "application/pdf",
+        [b'P', b'K', 3, 4, ..] => "application/zip",
+        [0x1f, 0x8b, ..] => "application/gzip",
+        [0, b'a', b's', b'm', ..] => "application/wasm",
+        _ => return sniff_markup(prefix),
+    };
+    Some(mime)
+}
+
+/// svg and html are text, look at their first tag
+fn sniff_markup(prefix: &[u8]) -> Option<&'static str> {
+    let text = String::from_utf8_lossy(prefix)
+        .trim_start()
+        .to_ascii_lowercase();
+    if text.starts_with("<svg") || (text.starts_with("<?xml") && text.contains("<svg")) {
+        Some("image/svg+xml")
+    } else if text.starts_with("<!doctype html") || text.starts_with("<html") {
+        Some("text/html")
+    } else {
+        None
+    }
+}
+
+/// the preferred file extension of a mime type, parameters are ignored
+pub fn mime_extension(mime: &str) -> Option<&'static str> {
+    let mime = mime.split(';').next()?.trim().to_ascii_lowercase();
+    MIME_TYPES
+        .iter()
+        .find(|(_, m)| *m == mime)
+        .map(|&(ext, _)| ext)
+}
+
+/// write `data:<mime>;base64,...`, the mime type is sniffed from the content or `path`
+pub fn process_data_uri_encode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    path: Option<&Path>,
+) -> Result<&'static str> {
+    let mut prefix = Vec::new();
+    reader.take(SNIFF_LEN).read_to_end(&mut prefix)?;
+    let mime = sniff_mime(&prefix, path);
+
+    write!(writer, "data:{mime};base64,")?;
+    let mut reader = Cursor::new(prefix).chain(reader);
+    process_encode(&mut reader, writer, Base64Format::Standard)?;
+    Ok(mime)
+}
+
+/// read the header of a data uri, up to and including the comma,
+/// the payload is left in the reader
+pub fn read_data_uri_header(reader: &mut dyn BufRead) -> Result<DataUri> {
+    let mut header = Vec::new();
+    reader
+        .take(MAX_HEADER_LEN as u64)
+        .read_until(b',', &mut header)?;
+    ensure!(
+        header.pop() == Some(b','),
+        "Data uri header is missing its comma or is too long"
+    );
+    let header = String::from_utf8(header)?;
+    // leading whitespace, e.g. from copying out of a css file
+    let header = header
+        .trim_start()
+        .strip_prefix("data:")
+        .ok_or_else(|| anyhow!("Input is not a data uri, it doesn't start with data:"))?;
+
+    let (mime, base64) = match header.strip_suffix(";base64") {
+        Some(mime) => (mime, true),
+        None => (header, false),
+    };
+    let mime = match mime {
+        "" => "text/plain;charset=US-ASCII".to_string(),
+        mime if mime.starts_with(';') => format!("text/plain{mime}"),
+        mime => percent_decode(mime.as_bytes()).decode_utf8()?.into_owned(),
+    };
+    Ok(DataUri { mime, base64 })
+}
+
+/// decode the payload following a data uri header
+pub fn process_data_uri_decode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    uri: &DataUri,
+) -> Result<()> {
+    if uri.base64 {
+        // some producers use the url-safe alphabet or drop the padding
+        return process_decode(reader, writer, Base64Format::Auto);
+    }
+
+    let mut payload = Vec::new();
+    reader.read_to_end(&mut payload)?;
+    let payload = payload.trim_ascii_end();
+    if payload.iter().any(|b| b.is_ascii_whitespace()) {
+        bail!("Percent-encoded data uri payload contains whitespace");
+    }
+    writer.write_all(&percent_decode(payload).collect::<Vec<_>>())?;
+    Ok(())
+}
+
+/// decode a data uri into `output`, which gets the extension of its mime type
+/// when it has none, and return the header along with the path written
+pub fn process_data_uri_decode_file(
+    reader: &mut dyn BufRead,
+    output: &Path,
+) -> Result<(DataUri, PathBuf)> {
+    let uri = read_data_uri_header(reader)?;
+    let mut output = output.to_path_buf();
+    if let (None, Some(ext)) = (output.extension(), mime_extension(&uri.mime)) {
+        output.set_extension(ext);
+    }
+    // decode next to the output and rename, a bad payload keeps the old file
+    write_file_atomic(&output, |writer| {
+        process_data_uri_decode(reader, writer, &uri)
+    })?;
+    Ok((uri, output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_data_uri_encode() -> Result<()> {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let mut uri = Vec::new();
+        let mime = process_data_uri_encode(&mut &png[..], &mut uri, None)?;
+        assert_eq!(mime, "image/png");
+        assert_eq!(
+            String::from_utf8(uri)?,
+            "data:image/png;base64,iVBORw0KGgoAAAANSUhEUg=="
+        );
+
+        let css = Path::new("style.css");
+        assert_eq!(sniff_mime(b"body { color: red }", Some(css)), "text/css");
+        assert_eq!(
+            sniff_mime(b"<?xml version=\"1.0\"?><svg>", None),
+            "image/svg+xml"
+        );
+        assert_eq!(sniff_mime(b"hello", None), "text/plain");
+        assert_eq!(sniff_mime(b"\xff\xfe\0", None), "application/octet-stream");
+        assert_eq!(mime_extension("image/svg+xml"), Some("svg"));
+        assert_eq!(mime_extension("Text/Plain;charset=utf-8"), Some("txt"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_data_uri_decode() -> Result<()> {
+        let mut reader = " data:image/png;base64,iVBORw0KGgoAAAANSUhEUg==\n".as_bytes();
+        let uri = read_data_uri_header(&mut reader)?;
+        assert_eq!(
+            uri,
+            DataUri {
+                mime: "image/png".to_string(),
+                base64: true
+            }
+        );
+        let mut decoded = Vec::new();
+        process_data_uri_decode(&mut reader, &mut decoded, &uri)?;
+        assert_eq!(decoded, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR");
+
+        let mut reader = "data:,hello%20world".as_bytes();
+        let uri = read_data_uri_header(&mut reader)?;
+        assert_eq!(uri.mime, "text/plain;charset=US-ASCII");
+        let mut decoded = Vec::new();
+        process_data_uri_decode(&mut reader, &mut decoded, &uri)?;
+        assert_eq!(decoded, b"hello world");
+
+        assert!(read_data_uri_header(&mut "http://example.com".as_bytes()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_data_uri_decode_file() -> Result<()> {
+        let mut reader = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUg==".as_bytes();
+        let path = std::env::temp_dir().join("rcli-test-data-uri");
+        let (uri, output) = process_data_uri_decode_file(&mut reader, &path)?;
+        assert_eq!(uri.mime, "image/png");
+        assert_eq!(output, path.with_extension("png"));
+        assert_eq!(std::fs::read(&output)?, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR");
+
+        // an explicit extension is kept, a bad payload leaves the file as it was
+        let mut reader = "data:text/plain;base64,aGk*".as_bytes();
+        assert!(process_data_uri_decode_file(&mut reader, &output).is_err());
+        assert_eq!(std::fs::read(&output)?, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR");
+        std::fs::remove_file(output)?;
+        Ok(())
+    }
+}
@@ -1,59 +1,364 @@
 //! generate a random password
-use anyhow::Result;
-use rand::seq::SliceRandom;
-
-const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
-const NUMBER: &[u8] = b"0123456789";
-const SYMBOL: &[u8] = b"!@#$%^&*_";
-
-/// generate a random password
-pub fn process_genpass(
-    length: u8,
-    uppercase: bool,
-    lowercase: bool,
-    number: bool,
-    symbol: bool,
-) -> Result<String> {
-    let mut rng = rand::thread_rng();
-    let mut password = Vec::new();
-    let mut chars = Vec::new();
+use anyhow::{anyhow, ensure, Result};
+use rand::{CryptoRng, RngCore};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
 
-    // the following conditional judgments guarantee that there will be at least one, which guarantees randomly generated uncertainty
+pub(super) const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub(super) const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+pub(super) const NUMBER: &str = "0123456789";
+pub(super) const SYMBOL: &str = "!@#$%^&*_";
+/// characters easily confused with each other when read
+pub(super) const AMBIGUOUS: &str = "0O1lI";
+
+/// which characters a password is made of and how many of each it must contain
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PasswordPolicy {
+    /// exact password length
+    pub length: usize,
+    pub uppercase: bool,
+    pub lowercase: bool,
+    pub number: bool,
+    pub symbol: bool,
+    /// minimum counts, only honored for enabled classes
+    pub min_uppercase: usize,
+    pub min_lowercase: usize,
+    pub min_number: usize,
+    pub min_symbol: usize,
+    /// characters the target system rejects
+    pub forbidden: String,
+    /// drop characters that are easily confused, like 0/O and 1/l/I
+    pub exclude_ambiguous: bool,
+    /// allowed symbols instead of the default `!@#$%^&*_`
+    pub symbols: Option<String>,
+    /// completely custom alphabet, replaces the character classes above
+    pub charset: Option<String>,
+}
 
-    if uppercase {
-        password.push(*UPPER.choose(&mut rng).expect("UPPER won't be empty"));
-        chars.extend_from_slice(UPPER);
+/// a set of characters with the minimum number the password must contain
+struct CharClass {
+    chars: Vec<char>,
+    min: usize,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            length: 16,
+            uppercase: true,
+            lowercase: true,
+            number: true,
+            symbol: true,
+            min_uppercase: 1,
+            min_lowercase: 1,
+            min_number: 1,
+            min_symbol: 1,
+            forbidden: String::new(),
+            exclude_ambiguous: false,
+            symbols: None,
+            charset: None,
+        }
     }
+}
 
-    if lowercase {
-        password.push(*LOWER.choose(&mut rng).expect("LOWER won't be empty"));
-        chars.extend_from_slice(LOWER);
+impl PasswordPolicy {
+    /// the enabled character classes, without the forbidden characters
+    fn classes(&self) -> Result<Vec<CharClass>> {
+        if let Some(charset) = &self.charset {
+            let chars = self.allowed(charset);
+            ensure!(!chars.is_empty(), "All charset characters are forbidden");
+            return Ok(vec![CharClass { chars, min: 0 }]);
+        }
+
+        let symbols = self.symbols.as_deref().unwrap_or(SYMBOL);
+        let mut classes = Vec::new();
+        for (name, enabled, chars, min) in [
+            ("uppercase", self.uppercase, UPPER, self.min_uppercase),
+            ("lowercase", self.lowercase, LOWER, self.min_lowercase),
+            ("number", self.number, NUMBER, self.min_number),
+            ("symbol", self.symbol, symbols, self.min_symbol),
+        ] {
+            if !enabled {
+                continue;
+            }
+            let chars = self.allowed(chars);
+            if chars.is_empty() {
+                ensure!(min == 0, "All {name} characters are forbidden");
+                continue;
+            }
+            classes.push(CharClass { chars, min });
+        }
+        Ok(classes)
+    }
+
+    /// entropy in bits of a password drawn uniformly from the allowed characters
+    pub fn entropy(&self) -> Result<f64> {
+        let mut chars = self
+            .classes()?
+            .into_iter()
+            .flat_map(|c| c.chars)
+            .collect::<Vec<_>>();
+        chars.sort_unstable();
+        chars.dedup();
+        Ok(self.length as f64 * (chars.len() as f64).log2())
     }
 
-    if number {
-        password.push(*NUMBER.choose(&mut rng).expect("NUMBER won't be empty"));
-        chars.extend_from_slice(NUMBER);
+    /// the distinct characters of the set that aren't forbidden or excluded as ambiguous
+    fn allowed(&self, chars: &str) -> Vec<char> {
+        let mut ret = chars
+            .chars()
+            .filter(|c| !self.forbidden.contains(*c))
+            .filter(|c| !(self.exclude_ambiguous && AMBIGUOUS.contains(*c)))
+            .collect::<Vec<_>>();
+        ret.sort_unstable();
+        ret.dedup();
+        ret
     }
+}
+
+/// load a named policy from a toml file with one table per policy
+pub fn load_policy(path: impl AsRef<Path>, name: &str) -> Result<PasswordPolicy> {
+    let path = path.as_ref();
+    ensure!(path.exists(), "Policy file {} not found", path.display());
+    let content = std::fs::read_to_string(path)?;
+    let mut policies: HashMap<String, PasswordPolicy> = toml::from_str(&content)?;
+    policies
+        .remove(name)
+        .ok_or_else(|| anyhow!("Policy {name:?} not found in {}", path.display()))
+}
+
+/// where `--policy` looks without `--policy-file`: `$RCLI_POLICY_FILE`, then
+/// `rcli/policies.toml` under `$XDG_CONFIG_HOME` or `~/.config`
+pub fn default_policy_file() -> Option<PathBuf> {
+    policy_file_from(|name| std::env::var_os(name))
+}
+
+fn policy_file_from(var: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let non_empty = |name| var(name).filter(|value| !value.is_empty());
+    if let Some(file) = non_empty("RCLI_POLICY_FILE") {
+        return Some(file.into());
+    }
+    let config = non_empty("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config.join("rcli").join("policies.toml"))
+}
+
+/// generate a random password of exactly `policy.length` characters
+pub fn process_genpass(policy: &PasswordPolicy) -> Result<String> {
+    process_genpass_with_rng(&mut rand::thread_rng(), policy)
+}
+
+/// generate a password drawing every random choice from `rng`
+pub fn process_genpass_with_rng<R: RngCore + CryptoRng + ?Sized>(
+    rng: &mut R,
+    policy: &PasswordPolicy,
+) -> Result<String> {
+    let classes = policy.classes()?;
+
+    ensure!(policy.length > 0, "Password length must be positive");
+    ensure!(
+        !classes.is_empty(),
+        "At least one character class must be enabled"
+    );
+    let required = classes.iter().map(|c| c.min).sum::<usize>();
+    ensure!(
+        required <= policy.length,
+        "Minimum counts add up to {required} characters, more than the password length {}",
+        policy.length
+    );
+
+    let mut password = Vec::with_capacity(policy.length);
+    let mut chars = Vec::new();
 
-    if symbol {
-        password.push(*SYMBOL.choose(&mut rng).expect("SYMBOL won't be empty"));
-        chars.extend_from_slice(SYMBOL);
+    // the minimum counts of every class come first, which guarantees the class constraints
+    for class in &classes {
+        for _ in 0..class.min {
+            password.push(class.chars[uniform(rng, class.chars.len())]);
+        }
+        chars.extend_from_slice(&class.chars);
     }
+    // classes may overlap with custom symbols, don't let shared characters weigh more
+    chars.sort_unstable();
+    chars.dedup();
 
     // generate the rest of the password
-    for _ in 0..length {
-        let c = chars
-            .choose(&mut rng)
-            .expect("charts won't be empty in this context");
-        password.push(*c);
+    for _ in required..policy.length {
+        password.push(chars[uniform(rng, chars.len())]);
     }
 
-    // disrupt the order
-    password.shuffle(&mut rng);
+    // disrupt the order, fisher-yates
+    for i in (1..password.len()).rev() {
+        password.swap(i, uniform(rng, i + 1));
+    }
 
     // convert to string
-    let password = String::from_utf8(password)?;
+    Ok(password.into_iter().collect())
+}
+
+/// a uniform index below `n` by rejection sampling
+///
+/// Derived passwords must not change with a rand upgrade, so this doesn't rely on
+/// the internals of `SliceRandom::choose` and `shuffle`.
+fn uniform<R: RngCore + ?Sized>(rng: &mut R, n: usize) -> usize {
+    assert!(n > 0 && n <= u32::MAX as usize, "invalid range {n}");
+    let n = n as u64;
+    // the largest multiple of n not above 2^32, values from there on would skew the result
+    let limit = (1u64 << 32) / n * n;
+    loop {
+        let value = rng.next_u32() as u64;
+        if value < limit {
+            return (value % n) as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::test_util::{assert_seeded, assert_uniform};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_process_genpass() -> Result<()> {
+        let policy = PasswordPolicy {
+            length: 16,
+            min_number: 2,
+            min_symbol: 3,
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            let password = process_genpass(&policy)?;
+            assert_eq!(password.len(), 16);
+            assert!(password.chars().filter(|c| NUMBER.contains(*c)).count() >= 2);
+            assert!(password.chars().filter(|c| SYMBOL.contains(*c)).count() >= 3);
+        }
+
+        let policy = PasswordPolicy {
+            length: 4,
+            min_symbol: 2,
+            ..Default::default()
+        };
+        assert!(process_genpass(&policy).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_policy() -> Result<()> {
+        let policy = load_policy("fixtures/policies.toml", "corp-ad")?;
+        for _ in 0..100 {
+            let password = process_genpass(&policy)?;
+            assert_eq!(password.len(), policy.length);
+            assert!(!password.contains(|c| policy.forbidden.contains(c)));
+        }
+
+        assert!(load_policy("fixtures/policies.toml", "not-exist").is_err());
+        Ok(())
+    }
 
-    Ok(password)
+    #[test]
+    fn test_default_policy_file() {
+        let lookup = |vars: &'static [(&str, &str)]| {
+            policy_file_from(move |name| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.into())
+            })
+        };
+        let all = &[
+            ("RCLI_POLICY_FILE", "/etc/rcli.toml"),
+            ("XDG_CONFIG_HOME", "/xdg"),
+            ("HOME", "/home/alice"),
+        ];
+        assert_eq!(lookup(all), Some("/etc/rcli.toml".into()));
+        assert_eq!(lookup(&all[1..]), Some("/xdg/rcli/policies.toml".into()));
+        assert_eq!(
+            lookup(&[("XDG_CONFIG_HOME", ""), ("HOME", "/home/alice")]),
+            Some("/home/alice/.config/rcli/policies.toml".into())
+        );
+        assert_eq!(lookup(&[]), None);
+    }
+
+    #[test]
+    fn test_process_genpass_with_rng() -> Result<()> {
+        let policy = PasswordPolicy {
+            length: 20,
+            min_uppercase: 3,
+            min_lowercase: 3,
+            min_number: 3,
+            min_symbol: 3,
+            ..Default::default()
+        };
+        assert_seeded(|rng| process_genpass_with_rng(rng, &policy))?;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        for _ in 0..10 {
+            let password = process_genpass_with_rng(&mut rng, &policy)?;
+            for class in [UPPER, LOWER, NUMBER, SYMBOL] {
+                assert!(password.chars().filter(|c| class.contains(*c)).count() >= 3);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_genpass_distribution() -> Result<()> {
+        let policy = PasswordPolicy {
+            length: 100,
+            uppercase: false,
+            lowercase: false,
+            symbol: false,
+            min_number: 0,
+            ..Default::default()
+        };
+        assert_uniform(NUMBER, 100, |rng| process_genpass_with_rng(rng, &policy))
+    }
+
+    #[test]
+    fn test_process_genpass_charset() -> Result<()> {
+        let policy = PasswordPolicy {
+            exclude_ambiguous: true,
+            symbols: Some("-+".to_string()),
+            forbidden: "xyz".to_string(),
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            let password = process_genpass(&policy)?;
+            assert!(!password.contains(|c| AMBIGUOUS.contains(c) || "xyz!@#".contains(c)));
+            assert!(password.contains(['-', '+']));
+        }
+
+        let policy = PasswordPolicy {
+            length: 8,
+            charset: Some("ab".to_string()),
+            ..Default::default()
+        };
+        let password = process_genpass(&policy)?;
+        assert!(password.chars().all(|c| c == 'a' || c == 'b'));
+
+        let policy = PasswordPolicy {
+            charset: Some("ab".to_string()),
+            forbidden: "ab".to_string(),
+            ..Default::default()
+        };
+        assert!(process_genpass(&policy).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy_entropy() -> Result<()> {
+        let policy = PasswordPolicy {
+            length: 10,
+            charset: Some("0123456789abcdef".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(policy.entropy()?, 40.0);
+        Ok(())
+    }
 }
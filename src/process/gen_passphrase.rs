@@ -0,0 +1,218 @@
+//! generate a diceware passphrase
+use anyhow::{anyhow, ensure, Result};
+use rand::{seq::SliceRandom, CryptoRng, RngCore};
+
+use super::gen_pass::{AMBIGUOUS, NUMBER, SYMBOL};
+
+/// EFF large wordlist, one `<dice rolls>\t<word>` per line
+const EFF_LARGE_WORDLIST: &str = include_str!("../../assets/eff_large_wordlist.txt");
+
+/// how a passphrase is put together
+#[derive(Debug, Clone)]
+pub struct PassphraseOptions {
+    /// number of words
+    pub words: usize,
+    /// string between the words
+    pub separator: String,
+    /// capitalize the first letter of every word
+    pub capitalize: bool,
+    /// append a random digit
+    pub digit: bool,
+    /// append a random symbol
+    pub symbol: bool,
+    /// allowed symbols instead of the default `!@#$%^&*_`
+    pub symbol_set: Option<String>,
+    /// drop easily confused digits from the appended one
+    pub exclude_ambiguous: bool,
+    /// characters the appended digit and symbol must not be
+    pub forbidden: String,
+}
+
+impl Default for PassphraseOptions {
+    fn default() -> Self {
+        Self {
+            words: 6,
+            separator: "-".to_string(),
+            capitalize: false,
+            digit: false,
+            symbol: false,
+            symbol_set: None,
+            exclude_ambiguous: false,
+            forbidden: String::new(),
+        }
+    }
+}
+
+impl PassphraseOptions {
+    /// entropy in bits, capitalization and separators are fixed so they add nothing
+    pub fn entropy(&self) -> f64 {
+        let mut bits = self.words as f64 * (wordlist().len() as f64).log2();
+        if self.digit {
+            bits += (self.suffix_chars(NUMBER).len() as f64).log2();
+        }
+        if self.symbol {
+            bits += (self.suffix_chars(self.symbols()).len() as f64).log2();
+        }
+        bits
+    }
+
+    fn symbols(&self) -> &str {
+        self.symbol_set.as_deref().unwrap_or(SYMBOL)
+    }
+
+    /// the distinct characters an appended digit or symbol is drawn from,
+    /// the same way a policy password picks them
+    fn suffix_chars(&self, chars: &str) -> Vec<char> {
+        let mut ret = chars
+            .chars()
+            .filter(|c| !self.forbidden.contains(*c))
+            .filter(|c| !(self.exclude_ambiguous && AMBIGUOUS.contains(*c)))
+            .collect::<Vec<_>>();
+        ret.sort_unstable();
+        ret.dedup();
+        ret
+    }
+}
+
+fn wordlist() -> Vec<&'static str> {
+    EFF_LARGE_WORDLIST
+        .lines()
+        .filter_map(|line| line.split('\t').nth(1))
+        .collect()
+}
+
+/// generate a passphrase from the embedded wordlist
+pub fn process_genpassphrase(options: &PassphraseOptions) -> Result<String> {
+    process_genpassphrase_with_rng(&mut rand::thread_rng(), options)
+}
+
+/// generate a passphrase drawing every random choice from `rng`
+pub fn process_genpassphrase_with_rng<R: RngCore + CryptoRng + ?Sized>(
+    rng: &mut R,
+    options: &PassphraseOptions,
+) -> Result<String> {
+    ensure!(options.words > 0, "Passphrase needs at least one word");
+    let wordlist = wordlist();
+
+    let words = (0..options.words)
+        .map(|_| {
+            let word = wordlist.choose(rng).expect("wordlist won't be empty");
+            if options.capitalize {
+                capitalize(word)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut passphrase = words.join(&options.separator);
+
+    if options.digit {
+        let digits = options.suffix_chars(NUMBER);
+        let digit = digits
+            .choose(rng)
+            .ok_or_else(|| anyhow!("All digits are excluded"))?;
+        passphrase.push(*digit);
+    }
+    if options.symbol {
+        let symbols = options.suffix_chars(options.symbols());
+        let symbol = symbols
+            .choose(rng)
+            .ok_or_else(|| anyhow!("Symbol set must not be empty"))?;
+        passphrase.push(*symbol);
+    }
+
+    Ok(passphrase)
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::test_util::{assert_seeded, assert_uniform};
+
+    #[test]
+    fn test_process_genpassphrase() -> Result<()> {
+        assert_eq!(wordlist().len(), 7776);
+
+        let options = PassphraseOptions {
+            words: 4,
+            separator: ".".to_string(),
+            capitalize: true,
+            digit: true,
+            ..Default::default()
+        };
+        let passphrase = process_genpassphrase(&options)?;
+        let words = passphrase.split('.').collect::<Vec<_>>();
+        assert_eq!(words.len(), 4);
+        assert!(words.iter().all(|w| w.starts_with(char::is_uppercase)));
+        assert!(passphrase.ends_with(|c: char| c.is_ascii_digit()));
+        // log2(7776) = 12.925 bits per word
+        assert!((options.entropy() - (4.0 * 7776f64.log2() + 10f64.log2())).abs() < 1e-9);
+
+        // suffixes follow --symbols and --exclude-ambiguous like policy passwords
+        let options = PassphraseOptions {
+            words: 1,
+            digit: true,
+            symbol: true,
+            symbol_set: Some("++-".to_string()),
+            exclude_ambiguous: true,
+            ..Default::default()
+        };
+        for _ in 0..50 {
+            let passphrase = process_genpassphrase(&options)?;
+            let mut suffix = passphrase.chars().rev();
+            assert!("+-".contains(suffix.next().expect("symbol")));
+            assert!("23456789".contains(suffix.next().expect("digit")));
+        }
+        assert!((options.entropy() - (7776f64.log2() + 3.0 + 1.0)).abs() < 1e-9);
+
+        let options = PassphraseOptions {
+            symbol: true,
+            symbol_set: Some(String::new()),
+            ..Default::default()
+        };
+        assert!(process_genpassphrase(&options).is_err());
+
+        // --exclude removes characters from the suffixes too
+        let options = PassphraseOptions {
+            words: 1,
+            digit: true,
+            symbol: true,
+            forbidden: "!@#$%^&*012345678".to_string(),
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            assert!(process_genpassphrase(&options)?.ends_with("9_"));
+        }
+        let options = PassphraseOptions {
+            symbol: true,
+            forbidden: SYMBOL.to_string(),
+            ..Default::default()
+        };
+        assert!(process_genpassphrase(&options).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_genpassphrase_with_rng() -> Result<()> {
+        let options = PassphraseOptions {
+            words: 1,
+            digit: true,
+            ..Default::default()
+        };
+        assert_seeded(|rng| process_genpassphrase_with_rng(rng, &options))?;
+
+        // only the suffix counts, the word in front of it has no say
+        assert_uniform(NUMBER, 5000, |rng| {
+            let passphrase = process_genpassphrase_with_rng(rng, &options)?;
+            Ok(passphrase[passphrase.len() - 1..].to_string())
+        })
+    }
+}
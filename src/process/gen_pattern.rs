@@ -0,0 +1,252 @@
+//! generate a string matching a fixed format pattern
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use rand::{rngs::OsRng, seq::SliceRandom, CryptoRng, Rng, RngCore};
+
+use super::gen_pass::{LOWER, NUMBER, SYMBOL, UPPER};
+
+/// upper bound of a single `{n}` or `{n,m}` repetition
+const MAX_REPEAT: usize = 1024;
+
+/// a parsed pattern, in one of two styles
+///
+/// A template like `XXX-999-xxx-!!` uses `X`, `x`, `9` and `!` for an uppercase letter,
+/// a lowercase letter, a digit and a symbol, every other character is copied as is.
+/// As soon as a pattern contains `[`, `{` or `\` it is a regex subset instead:
+/// `[A-Z0-9]` classes, `\d` and `\w`, `{n}` or `{n,m}` repetitions, and `\` escaping
+/// the next non-letter. Other regex syntax such as `+`, `.` or `\s` is rejected rather
+/// than copied. In a regex every other character, `X`, `x`, `9` and `!` included, is
+/// copied as is, so `X-[A-Z]{2}` starts with a literal X.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    atoms: Vec<Atom>,
+}
+
+#[derive(Debug, Clone)]
+struct Atom {
+    chars: Vec<char>,
+    min: usize,
+    max: usize,
+    repeated: bool,
+}
+
+impl Pattern {
+    /// entropy in bits, repetitions with a range only count their minimum
+    pub fn entropy(&self) -> f64 {
+        self.atoms
+            .iter()
+            .map(|atom| atom.min as f64 * (atom.chars.len() as f64).log2())
+            .sum()
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let regex = s.contains(['[', '{', '\\']);
+        let mut atoms: Vec<Atom> = Vec::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            let set = match c {
+                'X' if !regex => UPPER.chars().collect(),
+                'x' if !regex => LOWER.chars().collect(),
+                '9' if !regex => NUMBER.chars().collect(),
+                '!' if !regex => SYMBOL.chars().collect(),
+                '\\' => match chars.next() {
+                    Some('d') => NUMBER.chars().collect(),
+                    Some('w') => [UPPER, LOWER, NUMBER, "_"].concat().chars().collect(),
+                    Some(c) if c.is_ascii_alphanumeric() => {
+                        bail!("Unsupported escape '\\{c}', only \\d and \\w are supported")
+                    }
+                    Some(c) => vec![c],
+                    None => bail!("Pattern ends with a dangling '\\'"),
+                },
+                '[' => parse_class(&mut chars)?,
+                '{' => {
+                    let atom = atoms
+                        .last_mut()
+                        .ok_or_else(|| anyhow!("Repetition '{{' has nothing to repeat"))?;
+                    ensure!(!atom.repeated, "Repetition can't follow another repetition");
+                    (atom.min, atom.max) = parse_repeat(&mut chars)?;
+                    atom.repeated = true;
+                    continue;
+                }
+                '}' => bail!("Unmatched '}}' in pattern, escape it as '\\}}'"),
+                '+' | '*' | '?' | '.' | '(' | ')' | '|' | '^' | '$' if regex => {
+                    bail!("Unsupported regex syntax '{c}' in pattern, escape it as '\\{c}' for a literal")
+                }
+                c => vec![c],
+            };
+            atoms.push(Atom {
+                chars: set,
+                min: 1,
+                max: 1,
+                repeated: false,
+            });
+        }
+        ensure!(!atoms.is_empty(), "Pattern must not be empty");
+        Ok(Self { atoms })
+    }
+}
+
+/// parse the rest of a `[...]` class, ranges like `a-z` included
+fn parse_class(chars: &mut impl Iterator<Item = char>) -> Result<Vec<char>> {
+    let mut class = Vec::new();
+    let mut chars = chars.peekable();
+    ensure!(
+        chars.peek() != Some(&'^'),
+        "Negated character classes like [^...] aren't supported, list the allowed characters"
+    );
+    loop {
+        let c = match chars.next() {
+            Some(']') => break,
+            Some('\\') => chars
+                .next()
+                .ok_or_else(|| anyhow!("Unterminated character class"))?,
+            Some(c) => c,
+            None => bail!("Unterminated character class"),
+        };
+        if chars.peek() == Some(&'-') {
+            chars.next();
+            match chars.next() {
+                // a trailing '-' is literal
+                Some(']') => {
+                    class.extend([c, '-']);
+                    break;
+                }
+                Some(end) => {
+                    ensure!(c <= end, "Invalid range {c}-{end} in character class");
+                    class.extend(c..=end);
+                }
+                None => bail!("Unterminated character class"),
+            }
+        } else {
+            class.push(c);
+        }
+    }
+    class.sort_unstable();
+    class.dedup();
+    ensure!(!class.is_empty(), "Character class must not be empty");
+    Ok(class)
+}
+
+/// parse the rest of a `{n}` or `{n,m}` repetition
+fn parse_repeat(chars: &mut impl Iterator<Item = char>) -> Result<(usize, usize)> {
+    let mut body = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(c) => body.push(c),
+            None => bail!("Unterminated repetition {{{body}"),
+        }
+    }
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .map_err(|_| anyhow!("Invalid repetition {{{body}}}"))
+    };
+    let (min, max) = match body.split_once(',') {
+        Some((min, max)) => (parse(min)?, parse(max)?),
+        None => (parse(&body)?, parse(&body)?),
+    };
+    ensure!(
+        min <= max && max <= MAX_REPEAT,
+        "Invalid repetition {{{body}}}, expected n <= m <= {MAX_REPEAT}"
+    );
+    Ok((min, max))
+}
+
+/// generate a random string matching the pattern from the operating system rng
+pub fn process_genpattern(pattern: &Pattern) -> Result<String> {
+    process_genpattern_with_rng(&mut OsRng, pattern)
+}
+
+/// generate a random string matching the pattern drawing every random choice from `rng`
+pub fn process_genpattern_with_rng<R: RngCore + CryptoRng + ?Sized>(
+    rng: &mut R,
+    pattern: &Pattern,
+) -> Result<String> {
+    let mut s = String::new();
+    for atom in &pattern.atoms {
+        let count = rng.gen_range(atom.min..=atom.max);
+        for _ in 0..count {
+            s.push(*atom.chars.choose(rng).expect("class won't be empty"));
+        }
+    }
+    Ok(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::test_util::{assert_seeded, assert_uniform};
+
+    #[test]
+    fn test_process_genpattern() -> Result<()> {
+        let pattern: Pattern = "XXX-999-xxx-!!".parse()?;
+        let s = process_genpattern(&pattern)?;
+        let b = s.as_bytes();
+        assert_eq!(s.len(), 14);
+        assert!(b[..3].iter().all(u8::is_ascii_uppercase));
+        assert!(b[4..7].iter().all(u8::is_ascii_digit));
+        assert!(b[8..11].iter().all(u8::is_ascii_lowercase));
+        assert!(s[12..].chars().all(|c| SYMBOL.contains(c)));
+        assert_eq!((b[3], b[7], b[11]), (b'-', b'-', b'-'));
+
+        let pattern: Pattern = r"[A-Z]{3}-\d{4}".parse()?;
+        let s = process_genpattern(&pattern)?;
+        assert_eq!(s.len(), 8);
+        assert!(s[..3].bytes().all(|b| b.is_ascii_uppercase()));
+        assert!(s[4..].bytes().all(|b| b.is_ascii_digit()));
+        let expected = 3.0 * 26f64.log2() + 4.0 * 10f64.log2();
+        assert!((pattern.entropy() - expected).abs() < 1e-9);
+
+        let pattern: Pattern = r"[ab-]{2,5}\.".parse()?;
+        let s = process_genpattern(&pattern)?;
+        assert!((3..=6).contains(&s.len()) && s.ends_with('.'));
+
+        // template letters are literal in a regex
+        let pattern: Pattern = "X-9![a]{2}".parse()?;
+        assert_eq!(process_genpattern(&pattern)?, "X-9!aa");
+
+        for invalid in [
+            "",
+            "[A-Z",
+            "{3}",
+            "X{3",
+            "X{3,2}",
+            "X{a}",
+            "X{2}{3}",
+            "X{1}{3}",
+            "[^0O]{3}",
+            "X}",
+            "[z-a]",
+            "\\",
+            r"[A-Z]+-\s\d{2}.",
+            r"\d*",
+            r"\d?",
+            r"(\d){2}",
+            r"[ab]|\d",
+            r"^\d{3}$",
+            r"\X",
+            r"\s{2}",
+        ] {
+            assert!(invalid.parse::<Pattern>().is_err(), "{invalid}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_genpattern_with_rng() -> Result<()> {
+        let pattern: Pattern = r"[a-d]{1,4}".parse()?;
+        assert_seeded(|rng| process_genpattern_with_rng(rng, &pattern))?;
+
+        assert_uniform("abcd", 4000, |rng| process_genpattern_with_rng(rng, &pattern))?;
+        // the repetition is drawn uniformly as well
+        assert_uniform("1234", 4000, |rng| {
+            Ok(process_genpattern_with_rng(rng, &pattern)?.len().to_string())
+        })
+    }
+}
@@ -0,0 +1,160 @@
+//! generate a pronounceable password
+use anyhow::{ensure, Result};
+use rand::{seq::SliceRandom, CryptoRng, RngCore};
+
+use super::gen_pass::{NUMBER, SYMBOL};
+
+/// consonants that are hard to mishear when read aloud, no c/q/w/x/y
+const CONSONANT: &str = "bdfghjklmnprstvz";
+const VOWEL: &str = "aeiou";
+
+/// how a pronounceable password is put together
+#[derive(Debug, Clone, Default)]
+pub struct PronounceableOptions {
+    /// exact password length, including digits and symbols
+    pub length: usize,
+    /// digits appended after the syllables
+    pub digits: usize,
+    /// symbols appended after the digits
+    pub symbols: usize,
+    /// allowed symbols instead of the default `!@#$%^&*_`
+    pub symbol_set: Option<String>,
+}
+
+impl PronounceableOptions {
+    fn letters(&self) -> usize {
+        self.length.saturating_sub(self.digits + self.symbols)
+    }
+
+    /// the distinct allowed symbols, a repeated one would otherwise be favored
+    fn symbol_set(&self) -> Vec<char> {
+        let mut symbols = self
+            .symbol_set
+            .as_deref()
+            .unwrap_or(SYMBOL)
+            .chars()
+            .collect::<Vec<_>>();
+        symbols.sort_unstable();
+        symbols.dedup();
+        symbols
+    }
+
+    /// entropy in bits of the reduced space: fixed consonant/vowel positions
+    /// and digits/symbols at fixed places add nothing
+    pub fn entropy(&self) -> f64 {
+        let letters = self.letters();
+        let consonants = letters.div_ceil(2) as f64;
+        let vowels = (letters / 2) as f64;
+        consonants * (CONSONANT.len() as f64).log2()
+            + vowels * (VOWEL.len() as f64).log2()
+            + self.digits as f64 * (NUMBER.len() as f64).log2()
+            + self.symbols as f64 * (self.symbol_set().len() as f64).log2()
+    }
+}
+
+/// generate alternating consonant/vowel syllables, followed by the digits and symbols
+pub fn process_genpronounceable(options: &PronounceableOptions) -> Result<String> {
+    process_genpronounceable_with_rng(&mut rand::thread_rng(), options)
+}
+
+/// generate a pronounceable password drawing every random choice from `rng`
+pub fn process_genpronounceable_with_rng<R: RngCore + CryptoRng + ?Sized>(
+    rng: &mut R,
+    options: &PronounceableOptions,
+) -> Result<String> {
+    ensure!(
+        options.letters() > 0,
+        "Password length {} leaves no room for syllables after {} digits and {} symbols",
+        options.length,
+        options.digits,
+        options.symbols
+    );
+    let symbols = options.symbol_set();
+    ensure!(
+        options.symbols == 0 || !symbols.is_empty(),
+        "Symbol set must not be empty"
+    );
+
+    let consonants = CONSONANT.chars().collect::<Vec<_>>();
+    let vowels = VOWEL.chars().collect::<Vec<_>>();
+    let numbers = NUMBER.chars().collect::<Vec<_>>();
+
+    let mut password = String::with_capacity(options.length);
+    for i in 0..options.letters() {
+        let chars = if i % 2 == 0 { &consonants } else { &vowels };
+        password.push(*chars.choose(rng).expect("letters won't be empty"));
+    }
+    for _ in 0..options.digits {
+        password.push(*numbers.choose(rng).expect("NUMBER won't be empty"));
+    }
+    for _ in 0..options.symbols {
+        password.push(*symbols.choose(rng).expect("symbols won't be empty"));
+    }
+
+    Ok(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::test_util::{assert_seeded, assert_uniform};
+
+    #[test]
+    fn test_process_genpronounceable() -> Result<()> {
+        let options = PronounceableOptions {
+            length: 12,
+            digits: 2,
+            symbols: 1,
+            ..Default::default()
+        };
+        let password = process_genpronounceable(&options)?;
+        assert_eq!(password.len(), 12);
+        let (letters, rest) = password.split_at(9);
+        for (i, c) in letters.chars().enumerate() {
+            let set = if i % 2 == 0 { CONSONANT } else { VOWEL };
+            assert!(set.contains(c));
+        }
+        assert!(rest[..2].chars().all(|c| c.is_ascii_digit()));
+        assert!(SYMBOL.contains(&rest[2..]));
+
+        // 5 consonants * 4 bits + 4 vowels * log2(5) + 2 digits + 1 symbol
+        let expected = 20.0 + 4.0 * 5f64.log2() + 2.0 * 10f64.log2() + 9f64.log2();
+        assert!((options.entropy() - expected).abs() < 1e-9);
+
+        // repeated symbols add nothing
+        let options = PronounceableOptions {
+            length: 4,
+            symbols: 2,
+            symbol_set: Some("!!@!".to_string()),
+            ..Default::default()
+        };
+        assert!((options.entropy() - (4.0 + 5f64.log2() + 2.0)).abs() < 1e-9);
+
+        let options = PronounceableOptions {
+            length: 3,
+            digits: 3,
+            ..Default::default()
+        };
+        assert!(process_genpronounceable(&options).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_genpronounceable_with_rng() -> Result<()> {
+        let options = PronounceableOptions {
+            length: 10,
+            ..Default::default()
+        };
+        assert_seeded(|rng| process_genpronounceable_with_rng(rng, &options))?;
+
+        // consonants and vowels alternate, the vowels are drawn uniformly
+        assert_uniform(VOWEL, 1000, |rng| {
+            let password = process_genpronounceable_with_rng(rng, &options)?;
+            for (i, c) in password.chars().enumerate() {
+                let class = if i % 2 == 0 { CONSONANT } else { VOWEL };
+                assert!(class.contains(c), "{password}");
+            }
+            Ok(password)
+        })
+    }
+}
@@ -0,0 +1,137 @@
+//! generate api tokens, identifiers and pins
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{ensure, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use data_encoding::BASE32_NOPAD;
+use rand::{rngs::OsRng, CryptoRng, Rng, RngCore};
+use ulid::Ulid;
+use uuid::Builder;
+
+use crate::TokenKind;
+
+/// what token to generate and how long it is
+#[derive(Debug, Clone, Copy)]
+pub struct TokenOptions {
+    pub kind: TokenKind,
+    /// random bytes of hex, base64url and base32 tokens
+    pub bytes: usize,
+    /// digits of a pin
+    pub length: usize,
+}
+
+impl TokenOptions {
+    /// entropy in bits, only the random part of time based identifiers counts
+    pub fn entropy(&self) -> f64 {
+        match self.kind {
+            TokenKind::Hex | TokenKind::Base64Url | TokenKind::Base32 => self.bytes as f64 * 8.0,
+            // 6 of the 128 bits are version and variant
+            TokenKind::Uuid4 => 122.0,
+            // 48 bit timestamp, 6 bits version and variant
+            TokenKind::Uuid7 => 74.0,
+            // 48 bit timestamp
+            TokenKind::Ulid => 80.0,
+            TokenKind::Pin => self.length as f64 * 10f64.log2(),
+        }
+    }
+}
+
+/// generate a token from the operating system rng
+pub fn process_gentoken(options: &TokenOptions) -> Result<String> {
+    process_gentoken_with_rng(&mut OsRng, options)
+}
+
+/// generate a token drawing every random byte from `rng`
+pub fn process_gentoken_with_rng<R: RngCore + CryptoRng + ?Sized>(
+    rng: &mut R,
+    options: &TokenOptions,
+) -> Result<String> {
+    let token = match options.kind {
+        TokenKind::Hex | TokenKind::Base64Url | TokenKind::Base32 => {
+            ensure!(options.bytes > 0, "Token needs at least one byte");
+            let mut buf = vec![0u8; options.bytes];
+            rng.fill_bytes(&mut buf);
+            match options.kind {
+                TokenKind::Hex => buf.iter().map(|b| format!("{b:02x}")).collect(),
+                TokenKind::Base64Url => URL_SAFE_NO_PAD.encode(buf),
+                _ => BASE32_NOPAD.encode(&buf),
+            }
+        }
+        TokenKind::Uuid4 => Builder::from_random_bytes(rng.gen())
+            .into_uuid()
+            .to_string(),
+        TokenKind::Uuid7 => Builder::from_unix_timestamp_millis(unix_millis()?, &rng.gen())
+            .into_uuid()
+            .to_string(),
+        TokenKind::Ulid => Ulid::from_parts(unix_millis()?, rng.gen()).to_string(),
+        TokenKind::Pin => {
+            ensure!(options.length > 0, "Pin needs at least one digit");
+            (0..options.length)
+                .map(|_| char::from(b'0' + rng.gen_range(0..10)))
+                .collect()
+        }
+    };
+    Ok(token)
+}
+
+fn unix_millis() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::test_util::{assert_seeded, assert_uniform};
+
+    #[test]
+    fn test_process_gentoken() -> Result<()> {
+        let token = |kind| {
+            process_gentoken(&TokenOptions {
+                kind,
+                bytes: 16,
+                length: 6,
+            })
+        };
+
+        let hex = token(TokenKind::Hex)?;
+        assert_eq!(hex.len(), 32);
+        assert!(hex.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(
+            URL_SAFE_NO_PAD.decode(token(TokenKind::Base64Url)?)?.len(),
+            16
+        );
+        assert_eq!(
+            BASE32_NOPAD
+                .decode(token(TokenKind::Base32)?.as_bytes())?
+                .len(),
+            16
+        );
+
+        let uuid = uuid::Uuid::parse_str(&token(TokenKind::Uuid4)?)?;
+        assert_eq!(uuid.get_version_num(), 4);
+        let uuid = uuid::Uuid::parse_str(&token(TokenKind::Uuid7)?)?;
+        assert_eq!(uuid.get_version_num(), 7);
+        assert!(Ulid::from_string(&token(TokenKind::Ulid)?).is_ok());
+
+        let pin = token(TokenKind::Pin)?;
+        assert_eq!(pin.len(), 6);
+        assert!(pin.chars().all(|c| c.is_ascii_digit()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_gentoken_with_rng() -> Result<()> {
+        let options = TokenOptions {
+            kind: TokenKind::Pin,
+            bytes: 16,
+            length: 10,
+        };
+        assert_seeded(|rng| process_gentoken_with_rng(rng, &options))?;
+
+        assert_uniform("0123456789", 1000, |rng| {
+            let pin = process_gentoken_with_rng(rng, &options)?;
+            assert!(pin.len() == 10 && pin.bytes().all(|b| b.is_ascii_digit()), "{pin}");
+            Ok(pin)
+        })
+    }
+}
@@ -1,11 +1,60 @@
 mod b64;
+mod codec;
+mod csv_columnar;
 mod csv_convert;
+mod csv_watch;
+mod data_uri;
+mod gen_derive;
 mod gen_pass;
+mod gen_passphrase;
+mod gen_pattern;
+mod gen_pronounceable;
+mod gen_token;
 mod http_serve;
+mod otp;
+mod pass_hash;
+mod pass_strength;
+mod pwned;
 mod text;
 
+#[cfg(test)]
+mod test_util;
+
 pub use b64::{process_decode, process_encode};
-pub use csv_convert::process_csv;
-pub use gen_pass::process_genpass;
+pub use codec::{process_codec_decode, process_codec_encode, CodecOptions};
+pub use csv_convert::{
+    is_xml_name, process_csv, process_csv_decode, read_csv, CsvAnonymizer, CsvWriteOptions,
+};
+pub use csv_watch::process_csv_watch;
+pub use data_uri::{
+    mime_extension, process_data_uri_decode, process_data_uri_decode_file, process_data_uri_encode,
+    read_data_uri_header, sniff_mime, DataUri,
+};
+pub use gen_derive::process_genpass_derive;
+pub use gen_pass::{
+    default_policy_file, load_policy, process_genpass, process_genpass_with_rng, PasswordPolicy,
+};
+pub use gen_passphrase::{
+    process_genpassphrase, process_genpassphrase_with_rng, PassphraseOptions,
+};
+pub use gen_pattern::{process_genpattern, process_genpattern_with_rng, Pattern};
+pub use gen_pronounceable::{
+    process_genpronounceable, process_genpronounceable_with_rng, PronounceableOptions,
+};
+pub use gen_token::{process_gentoken, process_gentoken_with_rng, TokenOptions};
 pub use http_serve::process_http_serve;
-pub use text::{process_text_key_generate, process_text_sign, process_text_verify};
+pub use otp::{
+    process_otp_code, process_otp_secret, process_otp_uri, process_otp_verify, unix_time,
+    OtpParams, MAX_OTP_WINDOW,
+};
+pub use pass_hash::{
+    process_password_hash, process_password_hash_lines, process_password_verify, PasswordHashParams,
+};
+pub use pass_strength::{
+    process_password_check, process_password_strength, process_strong_password, PasswordReport,
+};
+pub use pwned::PwnedPasswords;
+pub use text::{
+    process_text_key_generate, process_text_key_generate_with_rng, process_text_sign,
+    process_text_verify,
+};
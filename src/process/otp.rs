@@ -0,0 +1,232 @@
+//! RFC 4226 HOTP and RFC 6238 TOTP one-time passwords
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, ensure, Result};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use subtle::ConstantTimeEq;
+
+use crate::{process_gentoken, OtpAlgorithm, OtpKind, TokenKind, TokenOptions};
+
+/// widest drift window, every counter in it is checked
+pub const MAX_OTP_WINDOW: u64 = 10;
+
+/// characters left alone in otpauth labels and parameters
+const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// shared secret and code parameters
+#[derive(Debug, Clone)]
+pub struct OtpParams {
+    pub secret: Vec<u8>,
+    pub algorithm: OtpAlgorithm,
+    pub digits: u32,
+    /// totp time step in seconds
+    pub period: u64,
+}
+
+impl OtpParams {
+    /// decode a base32 secret, spaces, dashes, padding and lower case are tolerated
+    pub fn try_new(
+        secret: &str,
+        algorithm: OtpAlgorithm,
+        digits: u32,
+        period: u64,
+    ) -> Result<Self> {
+        let secret = secret
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '='))
+            .collect::<String>()
+            .to_ascii_uppercase();
+        let secret = BASE32_NOPAD
+            .decode(secret.as_bytes())
+            .map_err(|e| anyhow!("Invalid base32 secret: {e}"))?;
+        ensure!(!secret.is_empty(), "Secret must not be empty");
+        ensure!((6..=9).contains(&digits), "Digits must be between 6 and 9");
+        ensure!(period > 0, "Period must be at least one second");
+        Ok(Self {
+            secret,
+            algorithm,
+            digits,
+            period,
+        })
+    }
+
+    /// the totp counter of a unix time
+    pub fn time_step(&self, time: u64) -> u64 {
+        time / self.period
+    }
+}
+
+/// smallest shared secret, RFC 4226 R6 asks for at least 128 bits
+const MIN_SECRET_LEN: usize = 16;
+
+/// generate a random base32 secret, 20 bytes matches the sha1 block recommendation
+pub fn process_otp_secret(bytes: usize) -> Result<String> {
+    ensure!(
+        bytes >= MIN_SECRET_LEN,
+        "Secret needs at least {MIN_SECRET_LEN} bytes (128 bits), 20 is recommended"
+    );
+    process_gentoken(&TokenOptions {
+        kind: TokenKind::Base32,
+        bytes,
+        length: 0,
+    })
+}
+
+/// build an otpauth:// uri for authenticator apps
+pub fn process_otp_uri(
+    params: &OtpParams,
+    kind: OtpKind,
+    issuer: &str,
+    account: &str,
+    counter: u64,
+) -> String {
+    let encode = |s| utf8_percent_encode(s, URI_COMPONENT).to_string();
+    let label = if issuer.is_empty() {
+        encode(account)
+    } else {
+        format!("{}:{}", encode(issuer), encode(account))
+    };
+    let mut uri = format!(
+        "otpauth://{kind}/{label}?secret={}&algorithm={}&digits={}",
+        BASE32_NOPAD.encode(&params.secret),
+        uri_algorithm(params.algorithm),
+        params.digits
+    );
+    if !issuer.is_empty() {
+        uri.push_str(&format!("&issuer={}", encode(issuer)));
+    }
+    match kind {
+        OtpKind::Totp => uri.push_str(&format!("&period={}", params.period)),
+        OtpKind::Hotp => uri.push_str(&format!("&counter={counter}")),
+    }
+    uri
+}
+
+fn uri_algorithm(algorithm: OtpAlgorithm) -> &'static str {
+    match algorithm {
+        OtpAlgorithm::Sha1 => "SHA1",
+        OtpAlgorithm::Sha256 => "SHA256",
+        OtpAlgorithm::Sha512 => "SHA512",
+    }
+}
+
+/// compute the code of a counter, for totp that is the time step
+pub fn process_otp_code(params: &OtpParams, counter: u64) -> Result<String> {
+    let counter = counter.to_be_bytes();
+    let digest = match params.algorithm {
+        OtpAlgorithm::Sha1 => hmac::<Hmac<Sha1>>(&params.secret, &counter)?,
+        OtpAlgorithm::Sha256 => hmac::<Hmac<Sha256>>(&params.secret, &counter)?,
+        OtpAlgorithm::Sha512 => hmac::<Hmac<Sha512>>(&params.secret, &counter)?,
+    };
+
+    // dynamic truncation, RFC 4226 section 5.3
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes(digest[offset..offset + 4].try_into()?) & 0x7fff_ffff;
+    let code = binary % 10u32.pow(params.digits);
+    Ok(format!("{code:0width$}", width = params.digits as usize))
+}
+
+fn hmac<M: Mac + hmac::digest::KeyInit>(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let mut mac = <M as Mac>::new_from_slice(key)?;
+    mac.update(data);
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+/// check a code against counters `counter - behind ..= counter + ahead`,
+/// returns the matching offset from `counter`
+pub fn process_otp_verify(
+    params: &OtpParams,
+    code: &str,
+    counter: u64,
+    behind: u64,
+    ahead: u64,
+) -> Result<Option<i64>> {
+    ensure!(
+        behind <= MAX_OTP_WINDOW && ahead <= MAX_OTP_WINDOW,
+        "Drift window can be at most {MAX_OTP_WINDOW} steps"
+    );
+    let code = code.trim();
+    let mut matched = None;
+    // check every counter of the window, so timing doesn't leak which one matched
+    for candidate in counter.saturating_sub(behind)..=counter.saturating_add(ahead) {
+        let expected = process_otp_code(params, candidate)?;
+        if bool::from(expected.as_bytes().ct_eq(code.as_bytes())) && matched.is_none() {
+            matched = Some(candidate as i64 - counter as i64);
+        }
+    }
+    Ok(matched)
+}
+
+/// seconds since the unix epoch
+pub fn unix_time() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the RFC 6238 test secrets, "12345678901234567890" repeated to the digest size
+    fn params(algorithm: OtpAlgorithm, secret: &[u8]) -> OtpParams {
+        OtpParams {
+            secret: secret.to_vec(),
+            algorithm,
+            digits: 8,
+            period: 30,
+        }
+    }
+
+    #[test]
+    fn test_process_otp_code() -> Result<()> {
+        let sha1 = params(OtpAlgorithm::Sha1, b"12345678901234567890");
+        let sha256 = params(OtpAlgorithm::Sha256, b"12345678901234567890123456789012");
+        let sha512 = params(
+            OtpAlgorithm::Sha512,
+            b"1234567890123456789012345678901234567890123456789012345678901234",
+        );
+        for (time, codes) in [
+            (59, ["94287082", "46119246", "90693936"]),
+            (1111111109, ["07081804", "68084774", "25091201"]),
+            (20000000000, ["65353130", "77737706", "47863826"]),
+        ] {
+            for (params, code) in [&sha1, &sha256, &sha512].into_iter().zip(codes) {
+                assert_eq!(process_otp_code(params, params.time_step(time))?, code);
+            }
+        }
+
+        // RFC 4226 appendix D
+        let hotp = OtpParams { digits: 6, ..sha1 };
+        assert_eq!(process_otp_code(&hotp, 0)?, "755224");
+        assert_eq!(process_otp_code(&hotp, 9)?, "520489");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_otp_verify() -> Result<()> {
+        assert!(process_otp_secret(10).is_err());
+        assert!(process_otp_secret(16).is_ok());
+        let secret = process_otp_secret(20)?;
+        let params = OtpParams::try_new(&secret.to_lowercase(), OtpAlgorithm::Sha1, 6, 30)?;
+        let code = process_otp_code(&params, 100)?;
+        assert_eq!(process_otp_verify(&params, &code, 100, 1, 1)?, Some(0));
+        assert_eq!(process_otp_verify(&params, &code, 101, 1, 1)?, Some(-1));
+        assert_eq!(process_otp_verify(&params, &code, 102, 1, 1)?, None);
+        assert!(process_otp_verify(&params, &code, 100, 0, MAX_OTP_WINDOW + 1).is_err());
+        assert!(process_otp_verify(&params, &code, 100, u64::MAX, 0).is_err());
+
+        let uri = process_otp_uri(&params, OtpKind::Totp, "ACME Co", "alice@acme.com", 0);
+        assert!(uri.starts_with("otpauth://totp/ACME%20Co:alice%40acme.com?secret="));
+        assert!(uri.ends_with("&digits=6&issuer=ACME%20Co&period=30"));
+
+        assert!(OtpParams::try_new("not base32!", OtpAlgorithm::Sha1, 6, 30).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+//! hash passwords into PHC strings and verify them
+use std::io::{BufRead, BufReader, Read};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use rand::rngs::OsRng;
+use scrypt::Scrypt;
+
+/// bcrypt only looks at this many bytes, anything after them would be silently ignored
+const BCRYPT_MAX_LEN: usize = 72;
+
+/// hash algorithm and its cost parameters
+#[derive(Debug, Clone, Copy)]
+pub enum PasswordHashParams {
+    /// memory in KiB, number of passes and lanes
+    Argon2id {
+        memory: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+    /// log2 of the number of rounds
+    Bcrypt { cost: u32 },
+    /// log2 of the cost factor, block size and parallelism
+    Scrypt { log_n: u8, r: u32, p: u32 },
+}
+
+/// hash a password with a random salt
+pub fn process_password_hash(password: &str, params: PasswordHashParams) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = match params {
+        PasswordHashParams::Argon2id {
+            memory,
+            iterations,
+            parallelism,
+        } => {
+            let params = Params::new(memory, iterations, parallelism, None)
+                .map_err(|e| anyhow!("Invalid argon2 parameters: {e}"))?;
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password(password.as_bytes(), &salt)
+                .map_err(|e| anyhow!("Argon2 failed: {e}"))?
+                .to_string()
+        }
+        PasswordHashParams::Bcrypt { cost } => {
+            check_bcrypt_len(password)?;
+            bcrypt::hash(password, cost)?
+        }
+        PasswordHashParams::Scrypt { log_n, r, p } => {
+            let params = scrypt::Params::new(log_n, r, p, scrypt::Params::RECOMMENDED_LEN)
+                .map_err(|e| anyhow!("Invalid scrypt parameters: {e}"))?;
+            Scrypt
+                .hash_password_customized(password.as_bytes(), None, None, params, &salt)
+                .map_err(|e| anyhow!("Scrypt failed: {e}"))?
+                .to_string()
+        }
+    };
+    Ok(hash)
+}
+
+/// hash every line of the reader, blank lines are skipped
+pub fn process_password_hash_lines(
+    reader: &mut dyn Read,
+    params: PasswordHashParams,
+) -> Result<Vec<(String, String)>> {
+    let mut hashes = Vec::new();
+    for line in BufReader::new(reader).lines() {
+        let password = line?;
+        let password = password.trim_end_matches('\r');
+        if password.is_empty() {
+            continue;
+        }
+        let hash = process_password_hash(password, params)?;
+        hashes.push((password.to_string(), hash));
+    }
+    Ok(hashes)
+}
+
+/// check a password against a PHC string, or a `$2b$` style string for bcrypt
+pub fn process_password_verify(password: &str, hash: &str) -> Result<bool> {
+    let hash = hash.trim();
+    if ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+    {
+        check_bcrypt_len(password)?;
+        return Ok(bcrypt::verify(password, hash)?);
+    }
+
+    let parsed = PasswordHash::new(hash).map_err(|e| anyhow!("Invalid password hash: {e}"))?;
+    let ret = match parsed.algorithm.as_str() {
+        "argon2id" | "argon2i" | "argon2d" => {
+            Argon2::default().verify_password(password.as_bytes(), &parsed)
+        }
+        "scrypt" => Scrypt.verify_password(password.as_bytes(), &parsed),
+        algorithm => bail!("Unsupported password hash algorithm: {algorithm}"),
+    };
+    match ret {
+        Ok(()) => Ok(true),
+        Err(password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(anyhow!("Password verification failed: {e}")),
+    }
+}
+
+fn check_bcrypt_len(password: &str) -> Result<()> {
+    ensure!(
+        password.len() <= BCRYPT_MAX_LEN,
+        "Password is {} bytes, bcrypt ignores everything after {BCRYPT_MAX_LEN}, use argon2id or scrypt",
+        password.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_password_hash_verify() -> Result<()> {
+        let params = [
+            PasswordHashParams::Argon2id {
+                memory: 1024,
+                iterations: 1,
+                parallelism: 1,
+            },
+            PasswordHashParams::Bcrypt { cost: 4 },
+            PasswordHashParams::Scrypt {
+                log_n: 4,
+                r: 8,
+                p: 1,
+            },
+        ];
+        for (params, prefix) in params
+            .into_iter()
+            .zip(["$argon2id$", "$2b$04$", "$scrypt$"])
+        {
+            let hash = process_password_hash("hunter2", params)?;
+            assert!(hash.starts_with(prefix), "{hash}");
+            assert!(process_password_verify("hunter2", &hash)?);
+            assert!(!process_password_verify("hunter3", &hash)?);
+        }
+
+        assert!(process_password_verify("hunter2", "$md5$abc").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_password_hash_bcrypt_len() -> Result<()> {
+        let params = PasswordHashParams::Bcrypt { cost: 4 };
+        let longest = "a".repeat(BCRYPT_MAX_LEN);
+        let hash = process_password_hash(&longest, params)?;
+        assert!(process_password_verify(&longest, &hash)?);
+
+        // otherwise every password sharing the first 72 bytes would match
+        let too_long = format!("{longest}b");
+        assert!(process_password_hash(&too_long, params).is_err());
+        assert!(process_password_verify(&too_long, &hash).is_err());
+        // multi-byte characters count in bytes
+        assert!(process_password_hash(&"é".repeat(37), params).is_err());
+        Ok(())
+    }
+}
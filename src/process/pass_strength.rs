@@ -0,0 +1,173 @@
+//! estimate password strength with zxcvbn
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Read};
+use zxcvbn::zxcvbn;
+
+use super::pwned::PwnedPasswords;
+
+/// strength report of a single password
+#[derive(Debug, Clone, Serialize)]
+pub struct PasswordReport {
+    pub password: String,
+    /// zxcvbn score from 0 (too guessable) to 4 (very unguessable)
+    pub score: u8,
+    /// entropy in bits, from the generator when known, otherwise log2 of the zxcvbn guesses
+    pub entropy: f64,
+    pub guesses: u64,
+    pub guesses_log10: f64,
+    pub crack_time_online_throttled: String,
+    pub crack_time_online: String,
+    pub crack_time_offline_slow: String,
+    pub crack_time_offline_fast: String,
+    /// why the password is weak, empty for strong passwords
+    pub warning: String,
+    /// how to make the password stronger, space separated sentences
+    pub suggestions: String,
+    /// times seen in breaches, only known when checked against pwned passwords
+    pub pwned: Option<u64>,
+}
+
+/// estimate the strength of a password, user inputs are words the attacker may know
+pub fn process_password_strength(
+    password: &str,
+    entropy: Option<f64>,
+    user_inputs: &[&str],
+) -> PasswordReport {
+    let result = zxcvbn(password, user_inputs);
+    let crack_times = result.crack_times();
+    let feedback = result.feedback();
+    let warning = feedback
+        .and_then(|f| f.warning())
+        .map(|w| w.to_string())
+        .unwrap_or_default();
+    let suggestions = feedback
+        .map(|f| {
+            f.suggestions()
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+
+    PasswordReport {
+        password: password.to_string(),
+        score: result.score().into(),
+        entropy: entropy.unwrap_or_else(|| (result.guesses() as f64).log2()),
+        guesses: result.guesses(),
+        guesses_log10: result.guesses_log10(),
+        crack_time_online_throttled: crack_times.online_throttling_100_per_hour().to_string(),
+        crack_time_online: crack_times.online_no_throttling_10_per_second().to_string(),
+        crack_time_offline_slow: crack_times
+            .offline_slow_hashing_1e4_per_second()
+            .to_string(),
+        crack_time_offline_fast: crack_times
+            .offline_fast_hashing_1e10_per_second()
+            .to_string(),
+        warning,
+        suggestions,
+        pwned: None,
+    }
+}
+
+/// keep generating until a password scores at least `min_score` against the user inputs
+/// and, when given, never appeared in a breach
+pub fn process_strong_password(
+    mut generate: impl FnMut() -> Result<String>,
+    entropy: f64,
+    min_score: u8,
+    max_attempts: u32,
+    user_inputs: &[&str],
+    pwned: Option<&PwnedPasswords>,
+) -> Result<PasswordReport> {
+    for _ in 0..max_attempts {
+        let mut report = process_password_strength(&generate()?, Some(entropy), user_inputs);
+        if let Some(pwned) = pwned {
+            report.pwned = Some(pwned.count(&report.password)?);
+        }
+        if report.score >= min_score && report.pwned.unwrap_or_default() == 0 {
+            return Ok(report);
+        }
+    }
+    let unpwned = if pwned.is_some() {
+        " outside of breaches"
+    } else {
+        ""
+    };
+    bail!(
+        "No password reached score {min_score}{unpwned} in {max_attempts} attempts, try a longer password or more characters"
+    )
+}
+
+/// check every non-empty line of the reader as a candidate password
+pub fn process_password_check(
+    reader: &mut dyn Read,
+    user_inputs: &[&str],
+    pwned: Option<&PwnedPasswords>,
+) -> Result<Vec<PasswordReport>> {
+    let mut reports = Vec::new();
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let password = line.trim_end_matches('\r');
+        if password.is_empty() {
+            continue;
+        }
+        let mut report = process_password_strength(password, None, user_inputs);
+        if let Some(pwned) = pwned {
+            report.pwned = Some(pwned.count(password)?);
+        }
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_password_strength() {
+        let report = process_password_strength("password", None, &[]);
+        assert_eq!(report.score, 0);
+        assert!(report.entropy < 10.0);
+
+        let report = process_password_strength("rFENj,.RK5wPeyC3", Some(95.0), &[]);
+        assert_eq!(report.score, 4);
+        assert_eq!(report.entropy, 95.0);
+        assert!(report.warning.is_empty());
+    }
+
+    #[test]
+    fn test_process_strong_password() -> Result<()> {
+        let mut candidates = ["password", "alice2024", "rFENj,.RK5wPeyC3"].into_iter();
+        let generate = || Ok(candidates.next().unwrap_or_default().to_string());
+        let report = process_strong_password(generate, 95.0, 4, 3, &[], None)?;
+        assert_eq!(report.password, "rFENj,.RK5wPeyC3");
+
+        let generate = || Ok("password".to_string());
+        assert!(process_strong_password(generate, 0.0, 1, 10, &[], None).is_err());
+
+        // a leaked password is rejected even when no minimum score is asked for
+        let pwned = PwnedPasswords::try_new("fixtures/pwned.txt")?;
+        let generate = || Ok("hunter2".to_string());
+        assert!(process_strong_password(generate, 0.0, 0, 10, &[], Some(&pwned)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_password_check() -> Result<()> {
+        let mut reader = "password\n\nalice-acme-2024\n".as_bytes();
+        let pwned = PwnedPasswords::try_new("fixtures/pwned")?;
+        let reports = process_password_check(&mut reader, &[], Some(&pwned))?;
+        assert_eq!(reports.len(), 2);
+        assert!(!reports[0].warning.is_empty());
+        assert_eq!(reports[0].pwned, Some(9545824));
+
+        // knowing the user and company makes the second password much weaker
+        let mut reader = "alice-acme-2024\n".as_bytes();
+        let with_inputs = process_password_check(&mut reader, &["alice", "acme"], None)?;
+        assert!(with_inputs[0].guesses < reports[1].guesses);
+        Ok(())
+    }
+}
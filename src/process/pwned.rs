@@ -0,0 +1,130 @@
+//! offline lookup in a local copy of the Pwned Passwords list
+use anyhow::{ensure, Result};
+use sha1::{Digest, Sha1};
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// local Pwned Passwords data, never touches the network
+///
+/// Either the single sorted `HASH:COUNT` file, searched with a binary search over the file,
+/// or a k-anonymity range directory with one `<first 5 hash chars>.txt` file of
+/// `SUFFIX:COUNT` lines per prefix, as written by the official downloader.
+#[derive(Debug, Clone)]
+pub struct PwnedPasswords {
+    path: PathBuf,
+}
+
+impl PwnedPasswords {
+    pub fn try_new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        ensure!(
+            path.exists(),
+            "Pwned passwords {} not found",
+            path.display()
+        );
+        Ok(Self { path: path.into() })
+    }
+
+    /// how often the password appears in breaches, 0 when it was never seen
+    pub fn count(&self, password: &str) -> Result<u64> {
+        let hash = format!("{:X}", Sha1::digest(password.as_bytes()));
+        if self.path.is_dir() {
+            let (prefix, suffix) = hash.split_at(5);
+            let range = self.path.join(format!("{prefix}.txt"));
+            if !range.exists() {
+                return Ok(0);
+            }
+            let reader = BufReader::new(File::open(range)?);
+            for line in reader.lines() {
+                if let Some(count) = parse_line(&line?, suffix) {
+                    return Ok(count);
+                }
+            }
+            Ok(0)
+        } else {
+            search_file(&mut File::open(&self.path)?, &hash)
+        }
+    }
+}
+
+/// the count of the `HASH:COUNT` line if its hash is the wanted one
+fn parse_line(line: &str, hash: &str) -> Option<u64> {
+    let (h, count) = line.trim_end().split_once(':')?;
+    if h.eq_ignore_ascii_case(hash) {
+        count.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// binary search over byte offsets of a file whose lines are sorted by hash
+fn search_file(file: &mut File, hash: &str) -> Result<u64> {
+    // a matching line, if any, starts somewhere in lo..hi
+    let (mut lo, mut hi) = (0, file.metadata()?.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let Some((start, line)) = line_at(file, mid)? else {
+            hi = mid;
+            continue;
+        };
+        let line_hash = line.split(':').next().unwrap_or_default();
+        match line_hash.to_ascii_uppercase().as_str().cmp(hash) {
+            Ordering::Equal => return Ok(parse_line(&line, hash).unwrap_or_default()),
+            Ordering::Less => lo = start + line.len() as u64,
+            Ordering::Greater => hi = mid,
+        }
+    }
+    Ok(0)
+}
+
+/// the first line starting at or after `offset` and where it starts, including its newline
+fn line_at(file: &mut File, offset: u64) -> Result<Option<(u64, String)>> {
+    let mut start = offset;
+    if offset > 0 {
+        // skip the rest of the line the offset falls into
+        file.seek(SeekFrom::Start(offset - 1))?;
+        let mut reader = BufReader::new(&mut *file);
+        let mut skipped = Vec::new();
+        start += reader.read_until(b'\n', &mut skipped)? as u64 - 1;
+    }
+
+    file.seek(SeekFrom::Start(start))?;
+    let mut line = String::new();
+    BufReader::new(&mut *file).read_line(&mut line)?;
+    Ok((!line.is_empty()).then_some((start, line)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pwned_file() -> Result<()> {
+        let pwned = PwnedPasswords::try_new("fixtures/pwned.txt")?;
+        assert_eq!(pwned.count("password")?, 9545824);
+        assert_eq!(pwned.count("hunter2")?, 17043);
+        assert_eq!(pwned.count("rFENj,.RK5wPeyC3")?, 0);
+
+        // every line of the file can be found
+        let content = std::fs::read_to_string("fixtures/pwned.txt")?;
+        let mut file = File::open("fixtures/pwned.txt")?;
+        for line in content.lines() {
+            let (hash, count) = line.split_once(':').expect("valid line");
+            assert_eq!(search_file(&mut file, hash)?, count.parse::<u64>()?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pwned_range_dir() -> Result<()> {
+        let pwned = PwnedPasswords::try_new("fixtures/pwned")?;
+        assert_eq!(pwned.count("password")?, 9545824);
+        assert_eq!(pwned.count("hunter2")?, 0);
+        assert!(PwnedPasswords::try_new("fixtures/not-exist").is_err());
+        Ok(())
+    }
+}
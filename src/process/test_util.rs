@@ -0,0 +1,42 @@
+//! checks shared by the tests of the seeded generators
+use std::fmt::Debug;
+
+use anyhow::Result;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+/// the same seed gives the same output, another seed a different one
+pub(super) fn assert_seeded<T: PartialEq + Debug>(
+    mut generate: impl FnMut(&mut ChaCha20Rng) -> Result<T>,
+) -> Result<()> {
+    let mut run = |seed| generate(&mut ChaCha20Rng::seed_from_u64(seed));
+    let first = run(42)?;
+    assert_eq!(first, run(42)?);
+    assert_ne!(first, run(43)?);
+    Ok(())
+}
+
+/// run `generate` `draws` times on a seed 7 rng and check every character of `alphabet`
+/// shows up within 10% of its fair share, other characters are not counted
+pub(super) fn assert_uniform(
+    alphabet: &str,
+    draws: usize,
+    mut generate: impl FnMut(&mut ChaCha20Rng) -> Result<String>,
+) -> Result<()> {
+    let mut rng = ChaCha20Rng::seed_from_u64(7);
+    let mut counts = vec![0usize; alphabet.chars().count()];
+    for _ in 0..draws {
+        for c in generate(&mut rng)?.chars() {
+            if let Some(i) = alphabet.chars().position(|a| a == c) {
+                counts[i] += 1;
+            }
+        }
+    }
+    let expected = counts.iter().sum::<usize>() / counts.len();
+    let band = expected - expected / 10..=expected + expected / 10;
+    assert!(
+        counts.iter().all(|n| band.contains(n)),
+        "{alphabet:?}: {counts:?} not within {band:?}"
+    );
+    Ok(())
+}
@@ -1,8 +1,8 @@
 //! Text sign/verify and key generate
-use crate::{process_genpass, TextSignFormat};
+use crate::{process_genpass_with_rng, PasswordPolicy, TextSignFormat};
 use anyhow::Result;
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
-use rand::rngs::OsRng;
+use rand::{rngs::OsRng, CryptoRng, RngCore};
 use std::{collections::HashMap, io::Read};
 
 /// signer trait
@@ -79,12 +79,27 @@ impl Blake3 {
     pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self> {
         let key = key.as_ref();
         // convert &[u8] to &[u8; 32]
-        let key = (&key[..32]).try_into()?;
+        let key = key
+            .get(..32)
+            .ok_or_else(|| anyhow::anyhow!("Blake3 key must be at least 32 bytes"))?
+            .try_into()?;
         Ok(Self::new(key))
     }
 
-    fn generate() -> Result<HashMap<&'static str, Vec<u8>>> {
-        let key = process_genpass(32, true, true, true, true)?;
+    /// generate a random key from the operating system rng
+    pub fn generate() -> Result<HashMap<&'static str, Vec<u8>>> {
+        Self::generate_with_rng(&mut OsRng)
+    }
+
+    /// generate a key drawing every random choice from `rng`
+    pub fn generate_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        rng: &mut R,
+    ) -> Result<HashMap<&'static str, Vec<u8>>> {
+        let policy = PasswordPolicy {
+            length: 32,
+            ..Default::default()
+        };
+        let key = process_genpass_with_rng(rng, &policy)?;
         let mut map = HashMap::new();
         map.insert("blake3.txt", key.as_bytes().to_vec());
         Ok(map)
@@ -103,9 +118,16 @@ impl Ed25519Signer {
         Ok(Self::new(key))
     }
 
-    fn generate() -> Result<HashMap<&'static str, Vec<u8>>> {
-        let mut csprng = OsRng;
-        let sk: SigningKey = SigningKey::generate(&mut csprng);
+    /// generate a random key pair from the operating system rng
+    pub fn generate() -> Result<HashMap<&'static str, Vec<u8>>> {
+        Self::generate_with_rng(&mut OsRng)
+    }
+
+    /// generate a key pair drawing every random byte from `rng`
+    pub fn generate_with_rng<R: RngCore + CryptoRng>(
+        rng: &mut R,
+    ) -> Result<HashMap<&'static str, Vec<u8>>> {
+        let sk: SigningKey = SigningKey::generate(rng);
         let pk: VerifyingKey = (&sk).into();
         let mut map = HashMap::new();
         map.insert("ed25519.sk", sk.to_bytes().to_vec());
@@ -160,10 +182,23 @@ pub fn process_text_key_generate(format: TextSignFormat) -> Result<HashMap<&'sta
     }
 }
 
+/// generate text key drawing every random choice from `rng`
+pub fn process_text_key_generate_with_rng<R: RngCore + CryptoRng>(
+    format: TextSignFormat,
+    rng: &mut R,
+) -> Result<HashMap<&'static str, Vec<u8>>> {
+    match format {
+        TextSignFormat::Black3 => Blake3::generate_with_rng(rng),
+        TextSignFormat::Ed25519 => Ed25519Signer::generate_with_rng(rng),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
     const KEY: &[u8] = include_bytes!("../../fixtures/blake3.txt");
 
     #[test]
@@ -187,4 +222,28 @@ mod tests {
         assert!(ret);
         Ok(())
     }
+
+    #[test]
+    fn test_process_text_key_generate_with_rng() -> Result<()> {
+        for format in [TextSignFormat::Black3, TextSignFormat::Ed25519] {
+            let generate = || {
+                let mut rng = ChaCha20Rng::seed_from_u64(42);
+                process_text_key_generate_with_rng(format, &mut rng)
+            };
+            assert_eq!(generate()?, generate()?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_blake3_generate_with_rng() -> Result<()> {
+        let generate = |seed| Blake3::generate_with_rng(&mut ChaCha20Rng::seed_from_u64(seed));
+        let key = generate(1)?;
+        assert_eq!(key, generate(1)?);
+        assert_ne!(key, generate(2)?);
+        let key = &key["blake3.txt"];
+        assert_eq!(key.len(), 32);
+        assert!(Blake3::try_new(key).is_ok());
+        Ok(())
+    }
 }
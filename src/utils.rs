@@ -1,7 +1,7 @@
 use anyhow::Result;
 use std::{
-    fs::File,
-    io::Read,
+    fs::{self, File},
+    io::{BufWriter, Read, Write},
     path::{Path, PathBuf},
 };
 
@@ -39,6 +39,65 @@ pub fn verify_path(path: &str) -> Result<PathBuf, &'static str> {
     }
 }
 
+/// write a file through a temporary sibling that is renamed over `path` only on success,
+/// so a failed write leaves an existing file untouched
+pub fn write_file_atomic<T>(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> Result<T>,
+) -> Result<T> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid output file {}", path.display()))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp = path.with_file_name(tmp_name);
+
+    let ret = File::create(&tmp).map_err(Into::into).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        let ret = write(&mut writer)?;
+        writer.into_inner()?.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(ret)
+    });
+    if ret.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    ret
+}
+
+/// format bytes like `hexdump -C`: offset, 16 hex bytes and their printable ascii
+pub fn hexdump(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, line) in data.chunks(16).enumerate() {
+        let hex = line
+            .iter()
+            .enumerate()
+            .map(|(j, b)| {
+                // an extra space between the two halves
+                if j == 8 {
+                    format!(" {b:02x}")
+                } else {
+                    format!("{b:02x}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = line
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        out.push_str(&format!("{:08x}  {hex:<48}  |{ascii}|\n", i * 16));
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,4 +119,39 @@ mod tests {
             Err("Path does not exist or is not a directory")
         );
     }
+
+    #[test]
+    fn test_write_file_atomic() -> Result<()> {
+        let dir = std::env::temp_dir();
+        let path = &dir.join("rcli-test.atomic");
+        fs::write(path, "old")?;
+        let ret = write_file_atomic::<()>(path, |writer| {
+            writer.write_all(b"partial")?;
+            anyhow::bail!("decode failed")
+        });
+        assert!(ret.is_err());
+        assert_eq!(fs::read_to_string(path)?, "old");
+
+        write_file_atomic(path, |writer| Ok(writer.write_all(b"new")?))?;
+        assert_eq!(fs::read_to_string(path)?, "new");
+        // no temporary file is left behind
+        assert!(fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .all(|entry| !entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(".rcli-test.atomic")));
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let dump = hexdump(b"hello, world\x00\x01\x02\xff!");
+        assert_eq!(
+            dump,
+            "00000000  68 65 6c 6c 6f 2c 20 77  6f 72 6c 64 00 01 02 ff  |hello, world....|\n\
+             00000010  21                                                |!|\n"
+        );
+    }
 }